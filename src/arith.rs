@@ -0,0 +1,517 @@
+use crate::vars;
+
+/// Operators, longest first so that the tokenizer can match greedily.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "++", "--", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "*=", "/=", "%=",
+    "+=", "-=", "&=", "^=", "|=", "+", "-", "*", "/", "%", "<", ">", "&", "|", "^", "!", "~", "?",
+    ":", "=", ",", "(", ")",
+];
+
+/// How deeply variables whose values are themselves expressions may refer to each other.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+enum TokenKind {
+    Number(i64),
+    Name(Variable),
+    Operator(&'static str),
+    End,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: TokenKind,
+    /// Byte offset in the expression, used to point at the offending token in errors.
+    pos: usize,
+}
+
+/// A variable, or an array element `name[subscript]` with its subscript still unevaluated.
+#[derive(Clone, Debug, PartialEq)]
+struct Variable {
+    name: String,
+    subscript: Option<String>,
+}
+
+#[derive(Debug)]
+enum Expr {
+    Number(i64),
+    Variable(Variable),
+    Unary(&'static str, Box<Expr>),
+    /// `++x`, `x--` and friends: the variable, the step and whether the new value is returned.
+    Step(Variable, i64, bool),
+    /// A binary operator, along with the position of its right operand.
+    Binary(&'static str, Box<Expr>, Box<Expr>, usize),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    /// `x = e` or a compound assignment such as `x += e`, which stores the operator without `=`.
+    Assign(Variable, Option<&'static str>, Box<Expr>, usize),
+}
+
+/// Evaluates an arithmetic expression as found in `$(( ))`, `(( ))` and `let`.
+///
+/// Arithmetic is done on wrapping 64-bit integers with C operator precedence.
+/// Variables may be referenced without a `$`; unset or empty ones count as 0,
+/// and a variable holding an expression is evaluated in turn. Errors are
+/// reported the way bash does, prefixed with the expression itself.
+pub fn evaluate(expression: &str) -> Result<i64, String> {
+    evaluate_at_depth(expression, 0).map_err(|message| format!("{}: {}", expression.trim(), message))
+}
+
+fn evaluate_at_depth(expression: &str, depth: usize) -> Result<i64, String> {
+    if depth > MAX_DEPTH {
+        return Err("expression recursion level exceeded".to_string());
+    }
+    let tokens = tokenize(expression)?;
+    if tokens.len() == 1 {
+        // An empty expression is 0.
+        return Ok(0);
+    }
+    let mut parser = Parser { source: expression, tokens, pos: 0 };
+    let expr = parser.comma()?;
+    if parser.peek() != &TokenKind::End {
+        return Err(parser.error("syntax error in expression"));
+    }
+    Evaluator { source: expression, depth }.eval(&expr)
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let bytes = source.as_bytes();
+    let mut i = 0;
+    while i < source.len() {
+        let c = bytes[i] as char;
+        if c.is_ascii_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < source.len() && (bytes[i].is_ascii_alphanumeric() || matches!(bytes[i], b'#' | b'@' | b'_')) {
+                i += 1;
+            }
+            let value = parse_number(&source[start..i])
+                .ok_or_else(|| token_error(number_error(&source[start..i]), &source[start..]))?;
+            tokens.push(Token { kind: TokenKind::Number(value), pos: start });
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < source.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                i += 1;
+            }
+            let name = source[start..i].to_string();
+            let mut subscript = None;
+            if bytes.get(i) == Some(&b'[') {
+                let close = matching_bracket(&source[i..])
+                    .ok_or_else(|| token_error("bad array subscript", &source[start..]))?;
+                subscript = Some(source[i + 1..i + close].to_string());
+                i += close + 1;
+            }
+            tokens.push(Token { kind: TokenKind::Name(Variable { name, subscript }), pos: start });
+        } else if let Some(op) = OPERATORS.iter().find(|op| source[i..].starts_with(*op)) {
+            tokens.push(Token { kind: TokenKind::Operator(op), pos: i });
+            i += op.len();
+        } else {
+            return Err(token_error("syntax error: invalid arithmetic operator", &source[i..]));
+        }
+    }
+    tokens.push(Token { kind: TokenKind::End, pos: source.len() });
+    Ok(tokens)
+}
+
+/// An error pointing at `token`, the rest of the expression from where it went
+/// wrong, without the blanks that may end the expression.
+fn token_error(message: &str, token: &str) -> String {
+    format!("{} (error token is \"{}\")", message, token.trim())
+}
+
+/// The offset of the `]` closing the `[` that `text` starts with, allowing for nested brackets.
+fn matching_bracket(text: &str) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses a decimal, `0x` hexadecimal, leading-zero octal or `base#digits` literal.
+fn parse_number(text: &str) -> Option<i64> {
+    let (base, digits) = if let Some((base, digits)) = text.split_once('#') {
+        (base.parse::<u32>().ok().filter(|base| (2..=64).contains(base))?, digits)
+    } else if let Some(hex) = text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        (16, hex)
+    } else if text.len() > 1 && text.starts_with('0') {
+        (8, &text[1..])
+    } else {
+        (10, text)
+    };
+    if digits.is_empty() {
+        return None;
+    }
+    let mut value: i64 = 0;
+    for c in digits.chars() {
+        // Digits beyond 9 are a-z, A-Z, @ and _; up to base 36 letters are case-insensitive.
+        let digit = match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 10,
+            'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+            'A'..='Z' => c as u32 - 'A' as u32 + 36,
+            '@' => 62,
+            '_' => 63,
+            _ => return None,
+        };
+        if digit >= base {
+            return None;
+        }
+        value = value.wrapping_mul(base as i64).wrapping_add(digit as i64);
+    }
+    Some(value)
+}
+
+fn number_error(text: &str) -> &'static str {
+    match text.split_once('#') {
+        Some((base, _)) if !base.parse::<u32>().is_ok_and(|base| (2..=64).contains(&base)) => "invalid arithmetic base",
+        _ => "value too great for base",
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &TokenKind {
+        &self.tokens[self.pos].kind
+    }
+
+    fn position(&self) -> usize {
+        self.tokens[self.pos].pos
+    }
+
+    fn advance(&mut self) -> TokenKind {
+        let kind = self.tokens[self.pos].kind.clone();
+        if kind != TokenKind::End {
+            self.pos += 1;
+        }
+        kind
+    }
+
+    /// Consumes the next token if it is one of `ops`.
+    fn eat(&mut self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            TokenKind::Operator(op) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    fn error(&self, message: &str) -> String {
+        token_error(message, &self.source[self.position()..])
+    }
+
+    fn comma(&mut self) -> Result<Expr, String> {
+        let mut expr = self.assignment()?;
+        while self.eat(&[","]).is_some() {
+            let pos = self.position();
+            expr = Expr::Binary(",", Box::new(expr), Box::new(self.assignment()?), pos);
+        }
+        Ok(expr)
+    }
+
+    fn assignment(&mut self) -> Result<Expr, String> {
+        let expr = self.conditional()?;
+        let ops = ["=", "*=", "/=", "%=", "+=", "-=", "<<=", ">>=", "&=", "^=", "|="];
+        let Some(op) = self.eat(&ops) else { return Ok(expr) };
+        let Expr::Variable(variable) = expr else {
+            self.pos -= 1;
+            return Err(self.error("attempted assignment to non-variable"));
+        };
+        let pos = self.position();
+        let value = self.assignment()?;
+        let op = op.strip_suffix('=').filter(|op| !op.is_empty());
+        Ok(Expr::Assign(variable, op, Box::new(value), pos))
+    }
+
+    fn conditional(&mut self) -> Result<Expr, String> {
+        let condition = self.binary(0)?;
+        if self.eat(&["?"]).is_none() {
+            return Ok(condition);
+        }
+        let then = self.comma()?;
+        if self.eat(&[":"]).is_none() {
+            return Err(self.error("`:' expected for conditional expression"));
+        }
+        let otherwise = self.conditional()?;
+        Ok(Expr::Conditional(Box::new(condition), Box::new(then), Box::new(otherwise)))
+    }
+
+    /// Left-associative binary operators, from loosest to tightest binding.
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: &[&[&str]] = &[
+            &["||"],
+            &["&&"],
+            &["|"],
+            &["^"],
+            &["&"],
+            &["==", "!="],
+            &["<=", ">=", "<", ">"],
+            &["<<", ">>"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        let Some(ops) = LEVELS.get(level) else { return self.power() };
+        let mut expr = self.binary(level + 1)?;
+        while let Some(op) = self.eat(ops) {
+            let pos = self.position();
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.binary(level + 1)?), pos);
+        }
+        Ok(expr)
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.unary()?;
+        if self.eat(&["**"]).is_none() {
+            return Ok(base);
+        }
+        let pos = self.position();
+        // `**` is right-associative.
+        Ok(Expr::Binary("**", Box::new(base), Box::new(self.power()?), pos))
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if let Some(op) = self.eat(&["++", "--"]) {
+            return match self.advance() {
+                TokenKind::Name(variable) => Ok(Expr::Step(variable, if op == "++" { 1 } else { -1 }, true)),
+                _ => {
+                    self.pos -= 1;
+                    Err(self.error("syntax error: operand expected"))
+                }
+            };
+        }
+        if let Some(op) = self.eat(&["-", "+", "!", "~"]) {
+            return Ok(Expr::Unary(op, Box::new(self.unary()?)));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Expr, String> {
+        let expr = self.primary()?;
+        if let Expr::Variable(variable) = &expr {
+            if let Some(op) = self.eat(&["++", "--"]) {
+                return Ok(Expr::Step(variable.clone(), if op == "++" { 1 } else { -1 }, false));
+            }
+        }
+        Ok(expr)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        match self.advance() {
+            TokenKind::Number(value) => Ok(Expr::Number(value)),
+            TokenKind::Name(variable) => Ok(Expr::Variable(variable)),
+            TokenKind::Operator("(") => {
+                let expr = self.comma()?;
+                if self.eat(&[")"]).is_none() {
+                    return Err(self.error("missing `)'"));
+                }
+                Ok(expr)
+            }
+            TokenKind::End => Err(self.error("syntax error: operand expected")),
+            TokenKind::Operator(_) => {
+                self.pos -= 1;
+                Err(self.error("syntax error: operand expected"))
+            }
+        }
+    }
+}
+
+struct Evaluator<'a> {
+    source: &'a str,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn eval(&self, expr: &Expr) -> Result<i64, String> {
+        match expr {
+            Expr::Number(value) => Ok(*value),
+            Expr::Variable(variable) => self.variable(variable),
+            Expr::Unary(op, operand) => {
+                let value = self.eval(operand)?;
+                Ok(match *op {
+                    "-" => value.wrapping_neg(),
+                    "!" => (value == 0) as i64,
+                    "~" => !value,
+                    _ => value,
+                })
+            }
+            Expr::Step(variable, step, prefix) => {
+                let old = self.variable(variable)?;
+                let new = old.wrapping_add(*step);
+                self.store(variable, new)?;
+                Ok(if *prefix { new } else { old })
+            }
+            Expr::Binary("&&", lhs, rhs, _) => Ok((self.eval(lhs)? != 0 && self.eval(rhs)? != 0) as i64),
+            Expr::Binary("||", lhs, rhs, _) => Ok((self.eval(lhs)? != 0 || self.eval(rhs)? != 0) as i64),
+            Expr::Binary(op, lhs, rhs, pos) => {
+                let lhs = self.eval(lhs)?;
+                let rhs = self.eval(rhs)?;
+                self.apply(op, lhs, rhs, *pos)
+            }
+            Expr::Conditional(condition, then, otherwise) => {
+                if self.eval(condition)? != 0 {
+                    self.eval(then)
+                } else {
+                    self.eval(otherwise)
+                }
+            }
+            Expr::Assign(variable, op, value, pos) => {
+                let value = self.eval(value)?;
+                let value = match op {
+                    Some(op) => self.apply(op, self.variable(variable)?, value, *pos)?,
+                    None => value,
+                };
+                self.store(variable, value)?;
+                Ok(value)
+            }
+        }
+    }
+
+    fn apply(&self, op: &str, lhs: i64, rhs: i64, pos: usize) -> Result<i64, String> {
+        let error_token = || &self.source[pos..];
+        Ok(match op {
+            "," => rhs,
+            "|" => lhs | rhs,
+            "^" => lhs ^ rhs,
+            "&" => lhs & rhs,
+            "==" => (lhs == rhs) as i64,
+            "!=" => (lhs != rhs) as i64,
+            "<" => (lhs < rhs) as i64,
+            ">" => (lhs > rhs) as i64,
+            "<=" => (lhs <= rhs) as i64,
+            ">=" => (lhs >= rhs) as i64,
+            "<<" => lhs.wrapping_shl(rhs as u32),
+            ">>" => lhs.wrapping_shr(rhs as u32),
+            "+" => lhs.wrapping_add(rhs),
+            "-" => lhs.wrapping_sub(rhs),
+            "*" => lhs.wrapping_mul(rhs),
+            "/" | "%" if rhs == 0 => {
+                return Err(token_error("division by 0", error_token()));
+            }
+            "/" => lhs.wrapping_div(rhs),
+            "%" => lhs.wrapping_rem(rhs),
+            "**" if rhs < 0 => {
+                return Err(token_error("exponent less than 0", error_token()));
+            }
+            "**" => lhs.wrapping_pow(rhs.min(u32::MAX as i64) as u32),
+            _ => unreachable!("unknown arithmetic operator {}", op),
+        })
+    }
+
+    /// The array key a subscript selects: the text itself for an associative
+    /// array, otherwise an index, where a negative one counts back from the end.
+    fn key(&self, variable: &Variable) -> Result<Option<vars::Key>, String> {
+        let Some(subscript) = &variable.subscript else { return Ok(None) };
+        if vars::is_assoc(&variable.name) {
+            return Ok(Some(vars::Key::Name(subscript.clone())));
+        }
+        let index = evaluate_at_depth(subscript, self.depth + 1)?;
+        let index = if index < 0 { vars::next_index(&variable.name) as i64 + index } else { index };
+        match usize::try_from(index) {
+            Ok(index) => Ok(Some(vars::Key::Index(index))),
+            Err(_) => Err(format!("{}[{}]: bad array subscript", variable.name, subscript)),
+        }
+    }
+
+    fn store(&self, variable: &Variable, value: i64) -> Result<(), String> {
+        match self.key(variable)? {
+            Some(key) => vars::set_element(&variable.name, key, &value.to_string()),
+            None => vars::set(&variable.name, &value.to_string()),
+        }
+        Ok(())
+    }
+
+    /// A variable's value: unset and empty count as 0, anything else is itself an expression.
+    fn variable(&self, variable: &Variable) -> Result<i64, String> {
+        let value = match self.key(variable)? {
+            Some(key) => vars::get_element(&variable.name, &key),
+            None => vars::get(&variable.name),
+        };
+        let value = value.unwrap_or_default();
+        let value = value.trim();
+        if value.is_empty() {
+            return Ok(0);
+        }
+        if let Ok(number) = value.parse() {
+            return Ok(number);
+        }
+        evaluate_at_depth(value, self.depth + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_c_precedence() {
+        assert_eq!(evaluate("1 + 2 * 3"), Ok(7));
+        assert_eq!(evaluate("(1 + 2) * 3"), Ok(9));
+        assert_eq!(evaluate("2 ** 3 ** 2"), Ok(512));
+        assert_eq!(evaluate("-2 ** 2"), Ok(4));
+        assert_eq!(evaluate("1 < 2 && 3 > 4 || !0"), Ok(1));
+        assert_eq!(evaluate("5 > 3 ? 10 : 20"), Ok(10));
+        assert_eq!(evaluate("1, 2, 3"), Ok(3));
+        assert_eq!(evaluate(""), Ok(0));
+    }
+
+    #[test]
+    fn reads_every_base() {
+        assert_eq!(evaluate("0x1f"), Ok(31));
+        assert_eq!(evaluate("017"), Ok(15));
+        assert_eq!(evaluate("2#101"), Ok(5));
+        assert_eq!(evaluate("64#_"), Ok(63));
+        assert!(evaluate("08").is_err());
+    }
+
+    #[test]
+    fn assigns_and_steps_variables() {
+        assert_eq!(evaluate("arith_test_a = 4, arith_test_a *= 3"), Ok(12));
+        assert_eq!(vars::get("arith_test_a").as_deref(), Some("12"));
+        assert_eq!(evaluate("arith_test_a++"), Ok(12));
+        assert_eq!(evaluate("--arith_test_a"), Ok(12));
+        vars::set("arith_test_b", "arith_test_a + 1");
+        assert_eq!(evaluate("arith_test_b * 2"), Ok(26));
+        assert_eq!(evaluate("arith_test_unset + 1"), Ok(1));
+    }
+
+    #[test]
+    fn reads_and_writes_array_elements() {
+        vars::set_array("arith_test_list", vec!["10".into(), "20".into(), "30".into()]);
+        assert_eq!(evaluate("arith_test_list[1] + arith_test_list[-1]"), Ok(50));
+        assert_eq!(evaluate("arith_test_list[0]++"), Ok(10));
+        assert_eq!(vars::get_element("arith_test_list", &vars::Key::Index(0)).as_deref(), Some("11"));
+        assert_eq!(evaluate("arith_test_list[5] = 7, arith_test_list[5]"), Ok(7));
+        assert!(evaluate("arith_test_list[-9]").unwrap_err().contains("bad array subscript"));
+
+        vars::declare_array("arith_test_map", true).unwrap();
+        assert_eq!(evaluate("arith_test_map[two words] = 2, arith_test_map[two words] += 3"), Ok(5));
+        assert_eq!(vars::get_element("arith_test_map", &vars::Key::Name("two words".into())).as_deref(), Some("5"));
+    }
+
+    #[test]
+    fn errors_quote_the_trimmed_token() {
+        assert_eq!(evaluate("1 / 0 "), Err("1 / 0: division by 0 (error token is \"0\")".to_string()));
+        assert_eq!(evaluate("2 ** -1"), Err("2 ** -1: exponent less than 0 (error token is \"-1\")".to_string()));
+        assert_eq!(evaluate("1 +"), Err("1 +: syntax error: operand expected (error token is \"\")".to_string()));
+        assert_eq!(evaluate("1 @ 2 "), Err("1 @ 2: syntax error: invalid arithmetic operator (error token is \"@ 2\")".to_string()));
+        assert!(evaluate("3 = 4").unwrap_err().contains("attempted assignment to non-variable"));
+    }
+}
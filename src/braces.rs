@@ -0,0 +1,176 @@
+/// Performs brace expansion on an unexpanded word, returning the words it
+/// expands to in order: `a{b,c}d` gives `abd acd`, `{1..5..2}` gives `1 3 5`
+/// and `{a..c}` gives `a b c`. Quoted or escaped braces and `${...}` are left
+/// alone, as is anything that is not a valid brace expression, such as `{a}`.
+pub fn expand(word: &str) -> Vec<String> {
+    let Some((start, end, alternatives)) = find_expression(word) else {
+        return vec![word.to_string()];
+    };
+    let (preamble, postscript) = (&word[..start], &word[end + 1..]);
+    alternatives
+        .iter()
+        .flat_map(|alternative| expand(&format!("{}{}{}", preamble, alternative, postscript)))
+        .collect()
+}
+
+/// Finds the first brace expression, returning the positions of its braces and its alternatives.
+fn find_expression(word: &str) -> Option<(usize, usize, Vec<String>)> {
+    let bytes = word.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'\'' | b'"' => i = skip_quoted(bytes, i),
+            b'$' if bytes.get(i + 1) == Some(&b'{') => i = matching_brace(bytes, i + 1)?,
+            b'{' => {
+                if let Some(end) = matching_brace(bytes, i) {
+                    if let Some(alternatives) = alternatives(&word[i + 1..end]) {
+                        return Some((i, end, alternatives));
+                    }
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// The index of the last byte of the quoted string starting at `start`.
+fn skip_quoted(bytes: &[u8], start: usize) -> usize {
+    let quote = bytes[start];
+    let mut i = start + 1;
+    while i < bytes.len() && bytes[i] != quote {
+        if quote == b'"' && bytes[i] == b'\\' {
+            i += 1;
+        }
+        i += 1;
+    }
+    i
+}
+
+/// The index of the `}` closing the `{` at `start`, skipping quotes, escapes and nested braces.
+fn matching_brace(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'\'' | b'"' => i = skip_quoted(bytes, i),
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Splits the inside of a brace expression into its alternatives: the parts
+/// of a comma list, or the members of a sequence. `None` if it is neither.
+fn alternatives(inner: &str) -> Option<Vec<String>> {
+    let bytes = inner.as_bytes();
+    let mut parts = Vec::new();
+    let mut part_start = 0;
+    let mut depth = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'\'' | b'"' => i = skip_quoted(bytes, i),
+            b'$' if bytes.get(i + 1) == Some(&b'{') => i = matching_brace(bytes, i + 1).unwrap_or(bytes.len()),
+            b'{' => depth += 1,
+            b'}' => depth -= 1,
+            b',' if depth == 0 => {
+                parts.push(inner[part_start..i].to_string());
+                part_start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    if parts.is_empty() {
+        return sequence(inner);
+    }
+    parts.push(inner[part_start..].to_string());
+    Some(parts)
+}
+
+/// Expands `x..y` or `x..y..step`, where `x` and `y` are both integers or both
+/// single letters. Integers written with leading zeros are padded to the same width.
+fn sequence(inner: &str) -> Option<Vec<String>> {
+    let parts: Vec<&str> = inner.split("..").collect();
+    let (from, to, step) = match parts[..] {
+        [from, to] => (from, to, 1),
+        [from, to, step] => (from, to, step.parse::<i64>().ok()?),
+        _ => return None,
+    };
+    // The direction comes from the endpoints; only the size of the step matters.
+    let step = step.unsigned_abs().max(1) as usize;
+    if let (Ok(start), Ok(end)) = (from.parse::<i64>(), to.parse::<i64>()) {
+        let padded = |s: &str| s.trim_start_matches('-').len() > 1 && s.trim_start_matches('-').starts_with('0');
+        let width = if padded(from) || padded(to) { from.len().max(to.len()) } else { 0 };
+        return Some(
+            range(start, end, step)
+                .map(|n| match n < 0 {
+                    // The minus sign counts towards the width.
+                    true => format!("-{:0width$}", -n, width = width.saturating_sub(1)),
+                    false => format!("{:0width$}", n, width = width),
+                })
+                .collect(),
+        );
+    }
+    let letter = |s: &str| {
+        let mut chars = s.chars();
+        chars.next().filter(|c| c.is_ascii_alphabetic() && chars.next().is_none())
+    };
+    let (start, end) = (letter(from)?, letter(to)?);
+    Some(range(start as i64, end as i64, step).map(|c| (c as u8 as char).to_string()).collect())
+}
+
+fn range(start: i64, end: i64, step: usize) -> Box<dyn Iterator<Item = i64>> {
+    if start <= end {
+        Box::new((start..=end).step_by(step))
+    } else {
+        Box::new((end..=start).rev().step_by(step))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(list: &str) -> Vec<String> {
+        list.split(' ').map(str::to_string).collect()
+    }
+
+    #[test]
+    fn expands_comma_lists() {
+        assert_eq!(expand("a{b,c}d"), words("abd acd"));
+        assert_eq!(expand("{a,b}{1,2}"), words("a1 a2 b1 b2"));
+        assert_eq!(expand("x{a,{b,c}}"), words("xa xb xc"));
+        assert_eq!(expand("{,pre}fix"), words("fix prefix"));
+    }
+
+    #[test]
+    fn expands_sequences() {
+        assert_eq!(expand("{1..5..2}"), words("1 3 5"));
+        assert_eq!(expand("{3..1}"), words("3 2 1"));
+        assert_eq!(expand("{a..c}"), words("a b c"));
+        assert_eq!(expand("{01..3}"), words("01 02 03"));
+    }
+
+    #[test]
+    fn leaves_other_braces_alone() {
+        assert_eq!(expand("{a}"), words("{a}"));
+        assert_eq!(expand("${x,y}"), words("${x,y}"));
+        assert_eq!(expand("'{a,b}'"), words("'{a,b}'"));
+        assert_eq!(expand("\\{a,b}"), words("\\{a,b}"));
+        assert_eq!(expand("{1..a}"), words("{1..a}"));
+    }
+}
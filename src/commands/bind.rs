@@ -0,0 +1,95 @@
+use std::io::Write;
+
+use crate::commands::{Builtin, Io};
+use crate::keybindings::{self, Action};
+
+const USAGE: &str = "bind [-lpX] [-r keyseq] [-x keyseq:shell-command] [keyseq:readline-function ...]";
+
+pub struct Bind;
+
+impl Builtin for Bind {
+    fn name(&self) -> &'static str { "bind" }
+
+    fn help(&self) -> &'static str {
+        "bind [-lpX] [-r keyseq] [-x keyseq:shell-command] [keyseq:readline-function ...]\n    Set line editor key bindings. Each binding is written `\"keyseq\": function`,\n    where keyseq is quoted with \\C- and \\M- (or \\e) prefixes, as in \"\\C-x\\C-e\",\n    or is a key name such as Control-u.\n    -l  list the names of the editing functions\n    -p  list the bindings in a form that can be reused as input\n    -X  list the shell commands bound with -x\n    -r  remove the binding for KEYSEQ\n    -x  run SHELL-COMMAND when KEYSEQ is typed; it can read and change the\n        line being edited through READLINE_LINE and READLINE_POINT"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let mut status = 0;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let result = match arg.as_str() {
+                "-l" => {
+                    for name in keybindings::function_names() {
+                        let _ = writeln!(io.stdout, "{}", name);
+                    }
+                    Ok(())
+                }
+                "-p" => {
+                    for (keyseq, action) in keybindings::bindings() {
+                        if let Action::Function(name) = action {
+                            let _ = writeln!(io.stdout, "{}: {}", keyseq, name);
+                        }
+                    }
+                    Ok(())
+                }
+                "-X" => {
+                    for (keyseq, action) in keybindings::bindings() {
+                        if let Action::Command(command) = action {
+                            let _ = writeln!(io.stdout, "{}: \"{}\"", keyseq, command);
+                        }
+                    }
+                    Ok(())
+                }
+                "-r" | "-x" => {
+                    let Some(value) = args.next() else {
+                        let _ = writeln!(io.stderr, "bind: {}: option requires an argument", arg);
+                        let _ = writeln!(io.stderr, "bind: usage: {}", USAGE);
+                        return 2;
+                    };
+                    if arg == "-r" {
+                        keybindings::unbind(value)
+                    } else {
+                        split_binding(value).and_then(|(keyseq, command)| {
+                            let command = command.strip_prefix('"').and_then(|c| c.strip_suffix('"')).unwrap_or(command);
+                            keybindings::bind(keyseq, Action::Command(command.to_string()))
+                        })
+                    }
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    let _ = writeln!(io.stderr, "bind: {}: invalid option", arg);
+                    let _ = writeln!(io.stderr, "bind: usage: {}", USAGE);
+                    return 2;
+                }
+                _ => split_binding(arg)
+                    .and_then(|(keyseq, function)| keybindings::bind(keyseq, Action::Function(function.to_string()))),
+            };
+            if let Err(message) = result {
+                let _ = writeln!(io.stderr, "bind: {}", message);
+                status = 1;
+            }
+        }
+        status
+    }
+}
+
+/// Splits `keyseq: value` at the colon after the key sequence, which may itself
+/// contain colons when quoted.
+fn split_binding(binding: &str) -> Result<(&str, &str), String> {
+    let keyseq_end = match binding.strip_prefix('"') {
+        Some(rest) => {
+            let mut escaped = false;
+            rest.find(|c| {
+                let close = c == '"' && !escaped;
+                escaped = c == '\\' && !escaped;
+                close
+            })
+            .map(|close| close + 2)
+        }
+        None => binding.find(':'),
+    };
+    match keyseq_end.and_then(|end| Some((&binding[..end], binding[end..].strip_prefix(':')?))) {
+        Some((keyseq, value)) => Ok((keyseq.trim(), value.trim())),
+        None => Err(format!("{}: no key sequence terminator", binding)),
+    }
+}
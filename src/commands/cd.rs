@@ -1,38 +1,201 @@
 use std::env;
-use std::path::PathBuf;
-use std::io::ErrorKind;
-
-pub fn handle_cd_command(input: &str) -> bool {
-    if let Some(rest) = input.strip_prefix("cd ") {
-        let target = if rest == "~" || rest.starts_with("~/") {
-            match env::var("HOME") {
-                Ok(home) => {
-                    let mut path = PathBuf::from(home);
-                    if rest.len() > 1 {
-                        // Skip the "~/" part and append the rest of the path.
-                        path.push(&rest[2..]);
-                    }
-                    path
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+use nix::errno::Errno;
+
+use crate::commands::{shopt, z, Builtin, Io};
+use crate::shell::running_typed_command;
+use crate::vars;
+
+pub struct Cd;
+
+impl Builtin for Cd {
+    fn name(&self) -> &'static str { "cd" }
+
+    fn help(&self) -> &'static str {
+        "cd [-L|-P] [dir]\n    Change the current directory to DIR (default $HOME). `cd -` returns to\n    $OLDPWD. Relative names are also searched for in $CDPATH.\n    -L  follow symbolic links logically, resolving `..` textually (default)\n    -P  use the physical directory structure"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let mut physical = false;
+        let mut operands = Vec::new();
+        let mut options_done = false;
+        for arg in args {
+            match arg.as_str() {
+                "-L" if !options_done => physical = false,
+                "-P" if !options_done => physical = true,
+                "--" if !options_done => options_done = true,
+                _ => {
+                    options_done = true;
+                    operands.push(arg.as_str());
+                }
+            }
+        }
+
+        let (target, announce) = match operands.as_slice() {
+            [] => match env::var("HOME") {
+                Ok(home) => (home, false),
+                Err(_) => {
+                    let _ = writeln!(io.stderr, "cd: HOME not set");
+                    return 1;
                 }
+            },
+            ["-"] => match env::var("OLDPWD") {
+                Ok(old) => (old, true),
                 Err(_) => {
-                    eprintln!("cd: HOME environment variable not set");
-                    return true;
+                    let _ = writeln!(io.stderr, "cd: OLDPWD not set");
+                    return 1;
                 }
+            },
+            [dir] => (dir.to_string(), false),
+            _ => {
+                let _ = writeln!(io.stderr, "cd: too many arguments");
+                return 1;
             }
-        } else {
-            PathBuf::from(rest)
         };
 
-        if let Err(e) = env::set_current_dir(&target) {
-            if e.kind() == ErrorKind::NotFound {
-                // Print a fixed error message that does not include the OS error text.
-                eprintln!("cd: {}: No such file or directory", target.display());
-            } else {
-                // For other kinds of errors, you might want to show the full error.
-                eprintln!("cd: {}: {}", target.display(), e);
+        let (mut target, mut found_in_cdpath) = search_cdpath(&target);
+        if !target.is_dir() && shopt::is_enabled("cdable_vars") {
+            // The name of a variable holding a directory stands for that directory.
+            if let Some(dir) = operands.first().filter(|name| vars::is_valid_name(name)).and_then(|name| vars::get(name)) {
+                if Path::new(&dir).is_dir() {
+                    (target, found_in_cdpath) = (PathBuf::from(dir), true);
+                }
+            }
+        }
+        match change_directory(&target, physical) {
+            Ok(new_dir) => {
+                if announce || found_in_cdpath {
+                    let _ = writeln!(io.stdout, "{}", new_dir.display());
+                }
+                0
+            }
+            Err(e) => {
+                let _ = writeln!(io.stderr, "cd: {}: {}", target.display(), error_message(&e));
+                1
+            }
+        }
+    }
+}
+
+/// Looks `dir` up in `$CDPATH` unless it is absolute or explicitly relative.
+/// Returns the directory to use and whether it came from a non-empty CDPATH entry,
+/// in which case the new directory is printed, as POSIX requires.
+fn search_cdpath(dir: &str) -> (PathBuf, bool) {
+    let explicit = dir.starts_with('/') || dir == "." || dir == ".."
+        || dir.starts_with("./") || dir.starts_with("../");
+    if !explicit {
+        if let Ok(cdpath) = env::var("CDPATH") {
+            for base in cdpath.split(':') {
+                let candidate = Path::new(if base.is_empty() { "." } else { base }).join(dir);
+                if candidate.is_dir() {
+                    return (candidate, !base.is_empty());
+                }
+            }
+        }
+    }
+    (PathBuf::from(dir), false)
+}
+
+/// Changes directory and keeps `PWD`/`OLDPWD` up to date, returning the new `PWD`.
+///
+/// In logical mode `..` is resolved textually against `$PWD`, so stepping out of
+/// a symlinked directory goes back the way it came; physical mode resolves links.
+pub fn change_directory(target: &Path, physical: bool) -> std::io::Result<PathBuf> {
+    let old_pwd = logical_cwd();
+    let new_pwd = if physical {
+        env::set_current_dir(target)?;
+        env::current_dir()?
+    } else {
+        let logical = normalize(&old_pwd.join(target));
+        match env::set_current_dir(&logical) {
+            Ok(()) => logical,
+            // The textual path may not exist (e.g. `..` out of a symlink whose
+            // parent is gone); fall back to the physical route like bash does.
+            Err(_) => {
+                env::set_current_dir(target)?;
+                env::current_dir()?
+            }
+        }
+    };
+    env::set_var("OLDPWD", &old_pwd);
+    env::set_var("PWD", &new_pwd);
+    if running_typed_command() {
+        z::record_visit(&new_pwd);
+    }
+    Ok(new_pwd)
+}
+
+/// Describes a failed directory change the way bash does, as in "No such file
+/// or directory", without Rust's "(os error 2)" suffix.
+pub fn error_message(e: &io::Error) -> String {
+    match e.raw_os_error() {
+        Some(code) => Errno::from_i32(code).desc().to_string(),
+        None => e.to_string(),
+    }
+}
+
+/// Returns `$PWD` if it still names the current directory, else the physical path.
+pub fn logical_cwd() -> PathBuf {
+    let physical = env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
+    match env::var("PWD") {
+        Ok(pwd) if Path::new(&pwd).is_absolute() && same_file(Path::new(&pwd), &physical) => {
+            PathBuf::from(pwd)
+        }
+        _ => physical,
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (a.metadata(), b.metadata()) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+/// Resolves `.` and `..` components textually, without touching the filesystem.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
             }
+            other => normalized.push(other.as_os_str()),
         }
-        return true;
     }
-    false
-}
\ No newline at end of file
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_dots_textually() {
+        assert_eq!(normalize(Path::new("/a/b/../c/./d")), PathBuf::from("/a/c/d"));
+        assert_eq!(normalize(Path::new("/a/link/..")), PathBuf::from("/a"));
+        assert_eq!(normalize(Path::new("/..")), PathBuf::from("/"));
+        assert_eq!(normalize(Path::new("/a//b/")), PathBuf::from("/a/b"));
+    }
+
+    #[test]
+    fn searches_cdpath_for_relative_names() {
+        let base = env::temp_dir().join(format!("shell-test-{}-cdpath", std::process::id()));
+        std::fs::create_dir_all(base.join("proj")).unwrap();
+        env::set_var("CDPATH", format!(":{}", base.display()));
+
+        assert_eq!(search_cdpath("proj"), (base.join("proj"), true));
+        // An empty entry stands for the current directory and is not announced.
+        assert_eq!(search_cdpath("src"), (PathBuf::from("./src"), false));
+        assert_eq!(search_cdpath("./proj"), (PathBuf::from("./proj"), false));
+        assert_eq!(search_cdpath("/tmp"), (PathBuf::from("/tmp"), false));
+        assert_eq!(search_cdpath("missing"), (PathBuf::from("missing"), false));
+
+        env::remove_var("CDPATH");
+        let _ = std::fs::remove_dir_all(&base);
+    }
+}
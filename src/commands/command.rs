@@ -0,0 +1,118 @@
+use std::io::Write;
+
+use crate::commands::execute::run_file;
+use crate::commands::type_cmd::{classify, executables_in, CommandKind, LookupError};
+use crate::commands::{lookup, Builtin, Io, KEYWORDS};
+use crate::shell::run_argv;
+
+const USAGE: &str = "command [-pVv] command [arg ...]";
+
+/// The PATH searched by `command -p`, which finds the standard utilities
+/// whatever PATH currently holds.
+const DEFAULT_PATH: &str = "/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin";
+
+pub struct CommandBuiltin;
+
+impl Builtin for CommandBuiltin {
+    fn name(&self) -> &'static str { "command" }
+
+    fn help(&self) -> &'static str {
+        "command [-pVv] command [arg ...]\n    Run COMMAND with ARGs, or describe it.\n    -p  search a default PATH that finds the standard utilities\n    -v  print the path or name that would be invoked\n    -V  print a description like `type`"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut default_path, mut describe) = (false, None);
+        let mut args = args;
+        while let Some(flags) = args.first().and_then(|arg| arg.strip_prefix('-')).filter(|flags| !flags.is_empty()) {
+            args = &args[1..];
+            if flags == "-" {
+                break;
+            }
+            for flag in flags.chars() {
+                match flag {
+                    'p' => default_path = true,
+                    'v' | 'V' => describe = Some(flag),
+                    _ => {
+                        let _ = writeln!(io.stderr, "command: -{}: invalid option", flag);
+                        let _ = writeln!(io.stderr, "command: usage: {}", USAGE);
+                        return 2;
+                    }
+                }
+            }
+        }
+
+        let Some(flag) = describe else {
+            return match args.split_first() {
+                Some((name, rest)) if default_path && !name.contains('/') && lookup(name).is_none() => {
+                    match executables_in(name, DEFAULT_PATH).first() {
+                        Some(path) => run_file(path, name, rest),
+                        None => {
+                            let e = LookupError::NotFound(name.clone());
+                            println!("{}", e);
+                            e.status()
+                        }
+                    }
+                }
+                _ => run_argv(args),
+            };
+        };
+        let mut status = 0;
+        for name in args {
+            match kind_of(name, default_path) {
+                Some(CommandKind::File(path)) if flag == 'v' => {
+                    let _ = writeln!(io.stdout, "{}", path);
+                }
+                Some(_) if flag == 'v' => {
+                    let _ = writeln!(io.stdout, "{}", name);
+                }
+                Some(kind) => {
+                    let _ = writeln!(io.stdout, "{}", kind.describe(name));
+                }
+                None => {
+                    if flag == 'V' {
+                        let _ = writeln!(io.stderr, "command: {}: not found", name);
+                    }
+                    status = 1;
+                }
+            }
+        }
+        status
+    }
+}
+
+/// What `name` would run as, looking files up in [`DEFAULT_PATH`] for `-p`.
+fn kind_of(name: &str, default_path: bool) -> Option<CommandKind> {
+    if !default_path || name.contains('/') || KEYWORDS.contains(&name) || lookup(name).is_some() {
+        return classify(name, false).into_iter().next();
+    }
+    executables_in(name, DEFAULT_PATH).into_iter().next().map(CommandKind::File)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn status(args: &[&str]) -> i32 {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let mut io = Io { stdin: Box::new(io::empty()), stdout: Box::new(io::sink()), stderr: Box::new(io::sink()) };
+        CommandBuiltin.run(&args, &mut io)
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        assert_eq!(status(&["-x", "ls"]), 2);
+        assert_eq!(status(&["-vq", "ls"]), 2);
+        assert_eq!(status(&["-v", "--", "echo"]), 0);
+        assert_eq!(status(&["-pv", "shell-test-no-such-command"]), 1);
+    }
+
+    #[test]
+    fn searches_the_default_path_for_files_only() {
+        let Some(CommandKind::File(path)) = kind_of("sh", true) else { panic!("sh not found in {}", DEFAULT_PATH) };
+        assert!(DEFAULT_PATH.split(':').any(|dir| path == format!("{}/sh", dir)));
+        assert!(matches!(kind_of("echo", true), Some(CommandKind::Builtin)));
+        assert!(matches!(kind_of("[[", true), Some(CommandKind::Keyword)));
+        assert!(kind_of("shell-test-no-such-command", true).is_none());
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::Write;
+
+use crate::commands::{Builtin, Io};
+use crate::util::{assign, assign_compound, compound_assignment};
+use crate::vars::{self, Value};
+
+pub struct Declare;
+
+impl Builtin for Declare {
+    fn name(&self) -> &'static str { "declare" }
+
+    fn help(&self) -> &'static str {
+        "declare [-aAxp] [name[=value] ...]\n    Set variable values and attributes. Without NAMEs, display all shell variables.\n    -a  make NAMEs indexed arrays\n    -A  make NAMEs associative arrays\n    -x  export NAMEs to the environment\n    -p  display the attributes and value of each NAME"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut indexed, mut assoc, mut export, mut print) = (false, false, false, false);
+        let mut names = args;
+        while let Some((arg, rest)) = names.split_first() {
+            if arg == "--" {
+                names = rest;
+                break;
+            }
+            let Some(flags) = arg.strip_prefix('-').filter(|flags| !flags.is_empty()) else { break };
+            for flag in flags.chars() {
+                match flag {
+                    'a' => indexed = true,
+                    'A' => assoc = true,
+                    'x' => export = true,
+                    'p' => print = true,
+                    _ => {
+                        let _ = writeln!(io.stderr, "declare: -{}: invalid option", flag);
+                        let _ = writeln!(io.stderr, "declare: usage: declare [-aAxp] [name[=value] ...]");
+                        return 2;
+                    }
+                }
+            }
+            names = rest;
+        }
+
+        if names.is_empty() {
+            for name in vars::shell_names() {
+                if let Some(line) = declaration(&name) {
+                    let _ = writeln!(io.stdout, "{}", line);
+                }
+            }
+            return 0;
+        }
+        let mut status = 0;
+        for arg in names {
+            if print {
+                match declaration(arg) {
+                    Some(line) => {
+                        let _ = writeln!(io.stdout, "{}", line);
+                    }
+                    None => {
+                        let _ = writeln!(io.stderr, "declare: {}: not found", arg);
+                        status = 1;
+                    }
+                }
+                continue;
+            }
+            // Compound assignments arrive unexpanded; see `run_simple`.
+            let compound = compound_assignment(arg);
+            let assignment = vars::parse_assignment(arg);
+            let name = match (&compound, &assignment) {
+                (Some((name, _, _)), _) => *name,
+                (None, Some(assignment)) => assignment.name,
+                (None, None) if vars::is_valid_name(arg) => arg.as_str(),
+                (None, None) => {
+                    let _ = writeln!(io.stderr, "declare: `{}': not a valid identifier", arg);
+                    status = 1;
+                    continue;
+                }
+            };
+            if indexed || assoc || compound.is_some() {
+                if let Err(message) = vars::declare_array(name, assoc) {
+                    let _ = writeln!(io.stderr, "declare: {}: {}", name, message);
+                    status = 1;
+                    continue;
+                }
+            }
+            let assigned = match (compound, assignment) {
+                (Some((name, append, inner)), _) => assign_compound(name, append, inner),
+                (None, Some(assignment)) => assign(&assignment),
+                (None, None) => true,
+            };
+            if !assigned {
+                status = 1;
+            }
+            if export {
+                vars::export(name);
+            }
+        }
+        status
+    }
+}
+
+/// The `declare -p` line that would recreate `name`.
+fn declaration(name: &str) -> Option<String> {
+    let line = match vars::value(name)? {
+        Value::Scalar(value) => {
+            let flag = if vars::is_exported(name) { "-x" } else { "--" };
+            format!("declare {} {}={}", flag, name, quote(&value))
+        }
+        Value::Indexed(items) if items.is_empty() => format!("declare -a {}", name),
+        Value::Indexed(items) => {
+            let items: Vec<String> = items.iter().map(|(i, value)| format!("[{}]={}", i, quote(value))).collect();
+            format!("declare -a {}=({})", name, items.join(" "))
+        }
+        Value::Assoc(items) if items.is_empty() => format!("declare -A {}", name),
+        Value::Assoc(items) => {
+            let items: String = items.iter().map(|(key, value)| format!("[{}]={} ", key, quote(value))).collect();
+            format!("declare -A {}=({})", name, items)
+        }
+    };
+    Some(line)
+}
+
+/// Double-quotes a value so that the shell reads it back unchanged.
+pub fn quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | '$' | '`') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
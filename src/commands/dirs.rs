@@ -0,0 +1,255 @@
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::commands::cd::{change_directory, error_message, logical_cwd, normalize};
+use crate::commands::{Builtin, Io};
+
+/// Saved directories, most recent first. The current directory is implicitly
+/// entry 0 and is not stored here.
+static STACK: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// The full stack as `dirs` shows it: the current directory followed by the saved ones.
+fn full_stack() -> Vec<PathBuf> {
+    let mut full = vec![logical_cwd()];
+    full.extend(STACK.lock().unwrap().iter().cloned());
+    full
+}
+
+/// Resolves a `+N`/`-N` (or bare `N`) stack index against a stack of `len` entries.
+fn parse_index(spec: &str, len: usize) -> Option<usize> {
+    let (from_right, digits) = match spec.as_bytes().first()? {
+        b'+' => (false, &spec[1..]),
+        b'-' => (true, &spec[1..]),
+        _ => (false, spec),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let n: usize = digits.parse().ok()?;
+    if n >= len {
+        return None;
+    }
+    Some(if from_right { len - 1 - n } else { n })
+}
+
+/// Expands the directory-stack forms of tilde: `~N`, `~+N` and `~-N`.
+pub fn expand_stack_tilde(word: &str) -> Option<PathBuf> {
+    let spec = word.strip_prefix('~')?;
+    if spec.trim_start_matches(['+', '-']).is_empty() {
+        return None;
+    }
+    let full = full_stack();
+    parse_index(spec, full.len()).map(|i| full[i].clone())
+}
+
+fn display(path: &Path, long: bool) -> String {
+    let text = path.display().to_string();
+    if !long {
+        if let Ok(home) = env::var("HOME") {
+            if let Some(rest) = text.strip_prefix(&home) {
+                if rest.is_empty() || rest.starts_with('/') {
+                    return format!("~{}", rest);
+                }
+            }
+        }
+    }
+    text
+}
+
+fn print_stack(io: &mut Io) {
+    let line: Vec<String> = full_stack().iter().map(|p| display(p, false)).collect();
+    let _ = writeln!(io.stdout, "{}", line.join(" "));
+}
+
+/// Makes `full[0]` the current directory and stores the rest as the saved stack.
+fn apply(full: Vec<PathBuf>, change_dir: bool, name: &str, io: &mut Io) -> i32 {
+    let mut full = full.into_iter();
+    let top = full.next().unwrap_or_else(logical_cwd);
+    if change_dir {
+        if let Err(e) = change_directory(&top, false) {
+            let _ = writeln!(io.stderr, "{}: {}: {}", name, top.display(), error_message(&e));
+            return 1;
+        }
+    }
+    *STACK.lock().unwrap() = full.collect();
+    print_stack(io);
+    0
+}
+
+pub struct Dirs;
+
+impl Builtin for Dirs {
+    fn name(&self) -> &'static str { "dirs" }
+
+    fn help(&self) -> &'static str {
+        "dirs [-clpv] [+N] [-N]\n    Display the directory stack, current directory first.\n    -c  clear the stack\n    -l  do not abbreviate $HOME as ~\n    -p  print one entry per line\n    -v  print one entry per line, prefixed with its index"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut long, mut per_line, mut numbered) = (false, false, false);
+        let full = full_stack();
+        for arg in args {
+            match arg.as_str() {
+                "-c" => {
+                    STACK.lock().unwrap().clear();
+                    return 0;
+                }
+                "-l" => long = true,
+                "-p" => per_line = true,
+                "-v" => numbered = true,
+                spec => match parse_index(spec, full.len()) {
+                    Some(i) => {
+                        let _ = writeln!(io.stdout, "{}", display(&full[i], long));
+                        return 0;
+                    }
+                    None => {
+                        let _ = writeln!(io.stderr, "dirs: {}: directory stack index out of range", spec);
+                        return 1;
+                    }
+                },
+            }
+        }
+        let entries: Vec<String> = full.iter().map(|p| display(p, long)).collect();
+        if numbered {
+            for (i, entry) in entries.iter().enumerate() {
+                let _ = writeln!(io.stdout, "{:2}  {}", i, entry);
+            }
+        } else if per_line {
+            for entry in &entries {
+                let _ = writeln!(io.stdout, "{}", entry);
+            }
+        } else {
+            let _ = writeln!(io.stdout, "{}", entries.join(" "));
+        }
+        0
+    }
+}
+
+pub struct Pushd;
+
+impl Builtin for Pushd {
+    fn name(&self) -> &'static str { "pushd" }
+
+    fn help(&self) -> &'static str {
+        "pushd [-n] [+N | -N | dir]\n    Save the current directory on the stack and change to DIR. With no\n    arguments, swap the top two entries; +N/-N rotate the Nth entry to the top.\n    -n  update the stack without changing directory"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let change_dir = !args.iter().any(|a| a == "-n");
+        let operands: Vec<&String> = args.iter().filter(|a| *a != "-n").collect();
+        let mut full = full_stack();
+        match operands.as_slice() {
+            [] => {
+                if full.len() < 2 {
+                    let _ = writeln!(io.stderr, "pushd: no other directory");
+                    return 1;
+                }
+                full.swap(0, 1);
+                apply(full, change_dir, self.name(), io)
+            }
+            [spec] if spec.starts_with(['+', '-']) => match parse_index(spec, full.len()) {
+                Some(i) => {
+                    full.rotate_left(i);
+                    apply(full, change_dir, self.name(), io)
+                }
+                None => {
+                    let _ = writeln!(io.stderr, "pushd: {}: directory stack index out of range", spec);
+                    1
+                }
+            },
+            [dir] => {
+                if !change_dir {
+                    // Insert below the current directory, leaving it on top. A relative
+                    // name is resolved now, while it still means what it says.
+                    full.insert(1, normalize(&logical_cwd().join(dir)));
+                    return apply(full, false, self.name(), io);
+                }
+                match change_directory(Path::new(&dir), false) {
+                    Ok(_) => {
+                        STACK.lock().unwrap().insert(0, full.swap_remove(0));
+                        print_stack(io);
+                        0
+                    }
+                    Err(e) => {
+                        let _ = writeln!(io.stderr, "pushd: {}: {}", dir, error_message(&e));
+                        1
+                    }
+                }
+            }
+            _ => {
+                let _ = writeln!(io.stderr, "pushd: too many arguments");
+                1
+            }
+        }
+    }
+}
+
+pub struct Popd;
+
+impl Builtin for Popd {
+    fn name(&self) -> &'static str { "popd" }
+
+    fn help(&self) -> &'static str {
+        "popd [-n] [+N | -N]\n    Remove the top entry (or the Nth) from the directory stack and change to\n    the new top directory.\n    -n  update the stack without changing directory"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let change_dir = !args.iter().any(|a| a == "-n");
+        let operands: Vec<&String> = args.iter().filter(|a| *a != "-n").collect();
+        let mut full = full_stack();
+        if full.len() < 2 {
+            let _ = writeln!(io.stderr, "popd: directory stack empty");
+            return 1;
+        }
+        let index = match operands.as_slice() {
+            [] => 0,
+            [spec] => match parse_index(spec, full.len()) {
+                Some(i) => i,
+                None => {
+                    let _ = writeln!(io.stderr, "popd: {}: directory stack index out of range", spec);
+                    return 1;
+                }
+            },
+            _ => {
+                let _ = writeln!(io.stderr, "popd: too many arguments");
+                return 1;
+            }
+        };
+        // With -n the current directory stays put, so the top entry is never the one removed.
+        let index = if change_dir { index } else { index.max(1) };
+        full.remove(index);
+        apply(full, change_dir && index == 0, self.name(), io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_stack_indices_from_either_end() {
+        assert_eq!(parse_index("0", 3), Some(0));
+        assert_eq!(parse_index("+2", 3), Some(2));
+        assert_eq!(parse_index("-0", 3), Some(2));
+        assert_eq!(parse_index("-2", 3), Some(0));
+        assert_eq!(parse_index("+3", 3), None);
+        assert_eq!(parse_index("-", 3), None);
+        assert_eq!(parse_index("+1x", 3), None);
+        assert_eq!(parse_index("", 3), None);
+    }
+
+    #[test]
+    fn expands_tilde_stack_references() {
+        *STACK.lock().unwrap() = vec![PathBuf::from("/saved/one"), PathBuf::from("/saved/two")];
+        assert_eq!(expand_stack_tilde("~1"), Some(PathBuf::from("/saved/one")));
+        assert_eq!(expand_stack_tilde("~+2"), Some(PathBuf::from("/saved/two")));
+        assert_eq!(expand_stack_tilde("~-0"), Some(PathBuf::from("/saved/two")));
+        assert_eq!(expand_stack_tilde("~0"), Some(logical_cwd()));
+        assert_eq!(expand_stack_tilde("~3"), None);
+        assert_eq!(expand_stack_tilde("~+"), None);
+        assert_eq!(expand_stack_tilde("~user"), None);
+        STACK.lock().unwrap().clear();
+    }
+}
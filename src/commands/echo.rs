@@ -1,12 +1,136 @@
-use crate::util::parse_parameters;
+use std::io::Write;
+
+use crate::commands::{shopt, Builtin, Io};
+
+pub struct Echo;
+
+impl Builtin for Echo {
+    fn name(&self) -> &'static str { "echo" }
+
+    fn help(&self) -> &'static str {
+        "echo [-neE] [arg ...]\n    Write the arguments to standard output, separated by spaces.\n    -n  do not append a newline\n    -e  interpret backslash escapes such as \\n, \\t, \\0nnn, \\xHH and \\c\n    -E  do not interpret backslash escapes (default)"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut newline, mut escapes) = (true, shopt::is_enabled("xpg_echo"));
+        let mut operands = args;
+        // Only leading words made up entirely of known flags are options; `-x` or `--` print as-is.
+        while let Some(flags) = operands.first().and_then(|arg| arg.strip_prefix('-')) {
+            if flags.is_empty() || !flags.chars().all(|f| matches!(f, 'n' | 'e' | 'E')) {
+                break;
+            }
+            for flag in flags.chars() {
+                match flag {
+                    'n' => newline = false,
+                    'e' => escapes = true,
+                    _ => escapes = false,
+                }
+            }
+            operands = &operands[1..];
+        }
 
-pub fn handle_echo_command(input: &str) -> bool {
-    if let Some(rest) = input.strip_prefix("echo ") {
-        let args = parse_parameters(rest);
         // Join tokens with a space, as typical echo behavior.
-        let result = args.join(" ");
-        println!("{}", result);
-        return true;
+        let mut output = operands.join(" ").into_bytes();
+        if escapes {
+            let (expanded, stop) = interpret_escapes(&operands.join(" "), true);
+            output = expanded;
+            // `\c` suppresses everything after it, including the newline.
+            newline &= !stop;
+        }
+        if newline {
+            output.push(b'\n');
+        }
+        let _ = io.stdout.write_all(&output);
+        0
+    }
+}
+
+/// Expands backslash escapes as `echo -e` and `printf` do. Octal escapes are
+/// `\0nnn` in echo style and `\nnn` otherwise. The result is bytes, since `\xHH`
+/// and octal escapes stand for single bytes that need not be valid UTF-8. The
+/// flag reports whether `\c` was seen, in which case the returned text stops there.
+pub fn interpret_escapes(input: &str, echo_style: bool) -> (Vec<u8>, bool) {
+    let mut out = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+            continue;
+        }
+        let Some(escape) = chars.next() else {
+            out.push(b'\\');
+            break;
+        };
+        match escape {
+            'a' => out.push(b'\x07'),
+            'b' => out.push(b'\x08'),
+            'c' => return (out, true),
+            'e' | 'E' => out.push(b'\x1b'),
+            'f' => out.push(b'\x0c'),
+            'n' => out.push(b'\n'),
+            'r' => out.push(b'\r'),
+            't' => out.push(b'\t'),
+            'v' => out.push(b'\x0b'),
+            '\\' => out.push(b'\\'),
+            'x' => {
+                let digits = take_digits(&mut chars, 16, 2);
+                match u8::from_str_radix(&digits, 16) {
+                    Ok(byte) => out.push(byte),
+                    Err(_) => out.extend_from_slice(b"\\x"),
+                }
+            }
+            '0'..='7' if !echo_style || escape == '0' => {
+                // In echo style the leading 0 only introduces the (up to three) octal digits.
+                let mut digits = if echo_style { String::new() } else { escape.to_string() };
+                let max = if echo_style { 3 } else { 2 };
+                digits.push_str(&take_digits(&mut chars, 8, max));
+                out.push((u32::from_str_radix(&digits, 8).unwrap_or(0) & 0xff) as u8);
+            }
+            other => {
+                out.push(b'\\');
+                out.extend_from_slice(other.encode_utf8(&mut [0; 4]).as_bytes());
+            }
+        }
+    }
+    (out, false)
+}
+
+fn take_digits(chars: &mut std::iter::Peekable<std::str::Chars>, radix: u32, max: usize) -> String {
+    let mut digits = String::new();
+    while digits.len() < max {
+        match chars.peek() {
+            Some(&c) if c.is_digit(radix) => {
+                digits.push(c);
+                chars.next();
+            }
+            _ => break,
+        }
+    }
+    digits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn named_escapes_and_stop() {
+        assert_eq!(interpret_escapes("a\\tb\\n\\\\\\q", true), (b"a\tb\n\\\\q".to_vec(), false));
+        assert_eq!(interpret_escapes("é\\cignored", true), ("é".as_bytes().to_vec(), true));
+        assert_eq!(interpret_escapes("end\\", true), (b"end\\".to_vec(), false));
+    }
+
+    #[test]
+    fn hex_and_octal_escapes() {
+        assert_eq!(interpret_escapes("\\x41\\x4a\\xZ", true), (b"AJ\\xZ".to_vec(), false));
+        assert_eq!(interpret_escapes("\\0101\\0", true), (b"A\0".to_vec(), false));
+        assert_eq!(interpret_escapes("\\101\\0101", false), (b"A\x081".to_vec(), false));
+        assert_eq!(interpret_escapes("\\101", true), (b"\\101".to_vec(), false));
+    }
+
+    #[test]
+    fn escapes_above_ascii_are_raw_bytes() {
+        assert_eq!(interpret_escapes("\\xff\\0377", true), (vec![0xff, 0xff], false));
+        assert_eq!(interpret_escapes("\\377é", false), (vec![0xff, 0xc3, 0xa9], false));
     }
-    false
-}
\ No newline at end of file
+}
@@ -1,15 +1,49 @@
-use crate::commands::type_cmd::find_executable;
-use crate::util::parse_parameters;
+use crate::commands::type_cmd::{resolve_command, LookupError};
+use crate::traps;
+use nix::sys::signal::{self, SigHandler};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
 use std::process::Command;
 
-pub fn handle_execute_command(input: &str) -> bool {
-    if let Some((command, arguments)) = parse_parameters(input).split_first() {
-        if find_executable(command).is_some() {
-            // Run the command and ignore its exit status,
-            // letting the command print its own error messages to stderr.
-            let _ = Command::new(command).args(arguments).status();
-            return true;
+/// Runs an external program and returns its exit status, reporting lookup
+/// failures with the conventional 126/127 codes.
+pub fn run_external(command: &str, arguments: &[String]) -> i32 {
+    match resolve_command(command) {
+        Ok(path) => run_file(&path, command, arguments),
+        Err(e @ LookupError::NotFound(_)) => {
+            println!("{}", e);
+            e.status()
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            e.status()
+        }
+    }
+}
+
+/// Runs the program at `path`, already resolved from `command`, and returns its exit status.
+pub fn run_file(path: &str, command: &str, arguments: &[String]) -> i32 {
+    // Run the resolved path but keep the name the user typed as argv[0],
+    // letting the command print its own error messages to stderr.
+    // Collected before forking: the child may not allocate.
+    let caught = traps::caught_signals();
+    let mut child = Command::new(path);
+    child.arg0(command).args(arguments);
+    // SAFETY: between fork and exec the closure only calls signal(2),
+    // which is async-signal-safe.
+    unsafe {
+        child.pre_exec(move || {
+            // Trapped signals go back to their defaults in the child.
+            for &caught in &caught {
+                let _ = signal::signal(caught, SigHandler::SigDfl);
+            }
+            Ok(())
+        });
+    }
+    match child.status() {
+        Ok(status) => status.code().unwrap_or_else(|| 128 + status.signal().unwrap_or(0)),
+        Err(e) => {
+            eprintln!("{}: {}", command, e);
+            126
         }
     }
-    false
-}
\ No newline at end of file
+}
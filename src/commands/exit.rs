@@ -1,12 +1,57 @@
-use std::env;
-use std::process;
+use std::io::Write;
 
-pub fn handle_exit_command(input: &str) -> bool {
-    // Check if the command is exactly "pwd"
-    if input.trim() == "exit 0" {
-        process::exit(0);
+use crate::commands::{Builtin, Io};
+use crate::shell::{is_login_shell, last_status, request_exit};
 
-        return true;
+pub struct Exit;
+
+impl Builtin for Exit {
+    fn name(&self) -> &'static str { "exit" }
+
+    fn help(&self) -> &'static str {
+        "exit [n]\n    Exit the shell with a status of N. If N is omitted, the status is that\n    of the last command executed."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        exit_with(self.name(), args, io)
     }
-    false
-}
\ No newline at end of file
+}
+
+pub struct Logout;
+
+impl Builtin for Logout {
+    fn name(&self) -> &'static str { "logout" }
+
+    fn help(&self) -> &'static str {
+        "logout [n]\n    Exit a login shell with a status of N."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        if !is_login_shell() {
+            let _ = writeln!(io.stderr, "logout: not login shell: use `exit'");
+            return 1;
+        }
+        exit_with(self.name(), args, io)
+    }
+}
+
+/// Validates the optional status operand and asks the REPL to shut down.
+fn exit_with(name: &str, args: &[String], io: &mut Io) -> i32 {
+    let status = match args {
+        [] => last_status(),
+        [arg] => match arg.parse::<i64>() {
+            // Statuses are reported modulo 256, so `exit -1` yields 255.
+            Ok(n) => n.rem_euclid(256) as i32,
+            Err(_) => {
+                let _ = writeln!(io.stderr, "{}: {}: numeric argument required", name, arg);
+                2
+            }
+        },
+        _ => {
+            let _ = writeln!(io.stderr, "{}: too many arguments", name);
+            return 1;
+        }
+    };
+    request_exit(status);
+    status
+}
@@ -0,0 +1,287 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{LazyLock, Mutex};
+use std::time::SystemTime;
+
+use nix::unistd::{access, AccessFlags};
+
+use crate::commands::{Builtin, Io};
+
+/// Caches PATH lookups so neither completion nor execution has to scan every
+/// directory on each keystroke or command.
+#[derive(Default)]
+struct CommandTable {
+    /// The PATH value the table was built against; any change resets everything.
+    path_var: String,
+    /// Every executable reachable through PATH, mapped to its first occurrence.
+    index: Option<HashMap<String, String>>,
+    /// Modification times of the PATH directories when `index` was built.
+    dir_mtimes: Vec<(PathBuf, Option<SystemTime>)>,
+    /// Names resolved so far, as shown by `hash`, with their hit counts.
+    hashed: HashMap<String, (String, usize)>,
+}
+
+static TABLE: LazyLock<Mutex<CommandTable>> = LazyLock::new(|| Mutex::new(CommandTable::default()));
+
+impl CommandTable {
+    /// Drops everything if PATH changed since the table was last used.
+    fn sync_path(&mut self) {
+        let path_var = env::var("PATH").unwrap_or_default();
+        if path_var != self.path_var {
+            *self = CommandTable { path_var, ..CommandTable::default() };
+        }
+    }
+
+    /// Returns true if any PATH directory was modified since the index was built.
+    fn is_stale(&self) -> bool {
+        self.dir_mtimes.iter().any(|(dir, mtime)| dir_mtime(dir) != *mtime)
+    }
+
+    fn rebuild_index(&mut self) {
+        let mut index = HashMap::new();
+        let mut dir_mtimes = Vec::new();
+        for dir in self.path_var.split(':') {
+            // An empty PATH component means the current directory.
+            let dir = PathBuf::from(if dir.is_empty() { "." } else { dir });
+            dir_mtimes.push((dir.clone(), dir_mtime(&dir)));
+            let Ok(entries) = fs::read_dir(&dir) else { continue };
+            for entry in entries.flatten() {
+                let Ok(name) = entry.file_name().into_string() else { continue };
+                if index.contains_key(&name) {
+                    continue;
+                }
+                let path = entry.path();
+                if has_exec_bits(&path) {
+                    index.insert(name, path.to_string_lossy().into_owned());
+                }
+            }
+        }
+        self.index = Some(index);
+        self.dir_mtimes = dir_mtimes;
+        // Earlier resolutions may now be shadowed by a newly added executable.
+        self.hashed.clear();
+    }
+
+    fn index(&mut self) -> &HashMap<String, String> {
+        if self.index.is_none() || self.is_stale() {
+            self.rebuild_index();
+        }
+        self.index.get_or_insert_with(HashMap::new)
+    }
+
+    fn lookup(&mut self, name: &str) -> Option<String> {
+        self.sync_path();
+        if let Some((path, hits)) = self.hashed.get_mut(name) {
+            if is_executable(Path::new(path)) {
+                *hits += 1;
+                return Some(path.clone());
+            }
+        }
+        let path = self.index().get(name).cloned()?;
+        if !is_executable(Path::new(&path)) {
+            return None;
+        }
+        self.hashed.insert(name.to_string(), (path.clone(), 1));
+        Some(path)
+    }
+
+    /// Resolves like `lookup`, but leaves the remembered names and hit counts alone.
+    fn peek(&mut self, name: &str) -> Option<String> {
+        self.sync_path();
+        if let Some((path, _)) = self.hashed.get(name) {
+            if is_executable(Path::new(path)) {
+                return Some(path.clone());
+            }
+        }
+        let path = self.index().get(name).cloned()?;
+        is_executable(Path::new(&path)).then_some(path)
+    }
+}
+
+fn dir_mtime(dir: &Path) -> Option<SystemTime> {
+    fs::metadata(dir).and_then(|m| m.modified()).ok()
+}
+
+/// Cheap check used while indexing: a regular file with any execute bit set.
+fn has_exec_bits(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else { return false };
+    if !metadata.is_file() {
+        return false;
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        true
+    }
+}
+
+/// Returns true if `path` is a regular file the current user may execute.
+pub fn is_executable(path: &Path) -> bool {
+    has_exec_bits(path) && access(path, AccessFlags::X_OK).is_ok()
+}
+
+/// Resolves a command name through the hash table, falling back to the PATH index.
+pub fn lookup(name: &str) -> Option<String> {
+    TABLE.lock().unwrap().lookup(name)
+}
+
+/// Resolves a command name without remembering it, for callers that only look.
+pub fn peek(name: &str) -> Option<String> {
+    TABLE.lock().unwrap().peek(name)
+}
+
+/// Returns every executable name in PATH that starts with `prefix`.
+pub fn candidates(prefix: &str) -> Vec<String> {
+    let mut table = TABLE.lock().unwrap();
+    table.sync_path();
+    table
+        .index()
+        .keys()
+        .filter(|name| name.starts_with(prefix))
+        .cloned()
+        .collect()
+}
+
+const USAGE: &str = "hash [-r] [-p pathname] [-dt] [name ...]";
+
+pub struct Hash;
+
+impl Builtin for Hash {
+    fn name(&self) -> &'static str { "hash" }
+
+    fn help(&self) -> &'static str {
+        "hash [-r] [-p pathname] [-dt] [name ...]\n    Remember or display command locations.\n    -r  forget every remembered location\n    -d  forget the location of each NAME\n    -t  print the remembered location of each NAME\n    -p  use PATHNAME as the location of NAME"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let mut table = TABLE.lock().unwrap();
+        table.sync_path();
+        let operands = args.get(1..).unwrap_or_default();
+        let mut status = 0;
+        match args.first().map(String::as_str) {
+            None => {
+                if table.hashed.is_empty() {
+                    let _ = writeln!(io.stdout, "hash: hash table empty");
+                    return 0;
+                }
+                let mut entries: Vec<_> = table.hashed.values().collect();
+                entries.sort();
+                let _ = writeln!(io.stdout, "hits\tcommand");
+                for (path, hits) in entries {
+                    let _ = writeln!(io.stdout, "{:4}\t{}", hits, path);
+                }
+            }
+            Some("-r") => {
+                table.hashed.clear();
+                table.index = None;
+            }
+            Some("-d") => {
+                for name in operands {
+                    if table.hashed.remove(name).is_none() {
+                        let _ = writeln!(io.stderr, "hash: {}: not found", name);
+                        status = 1;
+                    }
+                }
+            }
+            Some("-t") => {
+                for name in operands {
+                    match table.hashed.get(name) {
+                        Some((path, _)) => {
+                            let _ = writeln!(io.stdout, "{}", path);
+                        }
+                        None => {
+                            let _ = writeln!(io.stderr, "hash: {}: not found", name);
+                            status = 1;
+                        }
+                    }
+                }
+            }
+            Some("-p") => match operands {
+                [path, name] => {
+                    table.hashed.insert(name.clone(), (path.clone(), 0));
+                }
+                _ => {
+                    let _ = writeln!(io.stderr, "hash: usage: {}", USAGE);
+                    status = 2;
+                }
+            },
+            Some(option) if option.starts_with('-') && option != "-" && option != "--" => {
+                let _ = writeln!(io.stderr, "hash: {}: invalid option", option);
+                let _ = writeln!(io.stderr, "hash: usage: {}", USAGE);
+                status = 2;
+            }
+            Some(first) => {
+                let names = if first == "--" { operands } else { args };
+                for name in names {
+                    if table.lookup(name).is_none() {
+                        let _ = writeln!(io.stderr, "hash: {}: not found", name);
+                        status = 1;
+                    }
+                }
+            }
+        }
+        status
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn index_goes_stale_when_a_path_directory_changes() {
+        let dir = env::temp_dir().join(format!("shell-test-{}-hash", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let old = UNIX_EPOCH + Duration::from_secs(1_000_000);
+        fs::File::open(&dir).unwrap().set_modified(old).unwrap();
+
+        let mut table = CommandTable { path_var: dir.to_string_lossy().into_owned(), ..CommandTable::default() };
+        assert!(table.index().get("fresh-tool").is_none());
+        table.hashed.insert("ls".to_string(), ("/bin/ls".to_string(), 3));
+        assert!(!table.is_stale());
+
+        let tool = dir.join("fresh-tool");
+        fs::write(&tool, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(table.is_stale());
+        assert_eq!(table.index().get("fresh-tool"), Some(&tool.to_string_lossy().into_owned()));
+        assert!(table.hashed.is_empty());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn skips_files_that_are_not_executable() {
+        let dir = env::temp_dir().join(format!("shell-test-{}-hash-modes", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (plain, runnable) = (dir.join("plain"), dir.join("runnable"));
+        fs::write(&plain, "").unwrap();
+        fs::write(&runnable, "").unwrap();
+        fs::set_permissions(&runnable, fs::Permissions::from_mode(0o700)).unwrap();
+        assert!(!is_executable(&plain));
+        assert!(is_executable(&runnable));
+        assert!(!is_executable(&dir));
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn rejects_unknown_options() {
+        let run = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+            let mut io = Io { stdin: Box::new(std::io::empty()), stdout: Box::new(std::io::sink()), stderr: Box::new(std::io::sink()) };
+            Hash.run(&args, &mut io)
+        };
+        assert_eq!(run(&["-x"]), 2);
+        assert_eq!(run(&["-d", "shell-test-no-such-command"]), 1);
+        assert_eq!(run(&["--", "shell-test-no-such-command"]), 1);
+    }
+}
@@ -0,0 +1,37 @@
+use std::io::Write;
+
+use crate::commands::{builtins, Builtin, Io};
+
+pub struct Help;
+
+impl Builtin for Help {
+    fn name(&self) -> &'static str { "help" }
+
+    fn help(&self) -> &'static str {
+        "help [pattern ...]\n    Describe builtins. Without arguments, list the usage of every builtin;\n    otherwise show the full help of each builtin whose name starts with PATTERN."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        if args.is_empty() {
+            let _ = writeln!(io.stdout, "Shell builtins:");
+            for builtin in builtins() {
+                let synopsis = builtin.help().lines().next().unwrap_or_default();
+                let _ = writeln!(io.stdout, "  {}", synopsis);
+            }
+            return 0;
+        }
+        let mut status = 0;
+        for pattern in args {
+            let mut matched = false;
+            for builtin in builtins().filter(|b| b.name().starts_with(pattern.as_str())) {
+                let _ = writeln!(io.stdout, "{}: {}", builtin.name(), builtin.help());
+                matched = true;
+            }
+            if !matched {
+                let _ = writeln!(io.stderr, "help: no help topics match `{}'.", pattern);
+                status = 1;
+            }
+        }
+        status
+    }
+}
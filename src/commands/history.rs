@@ -0,0 +1,151 @@
+use std::ffi::CString;
+use std::io::Write;
+
+use nix::libc;
+
+use crate::commands::cd::logical_cwd;
+use crate::commands::{Builtin, Io};
+use crate::history;
+use crate::vars;
+
+const USAGE: &str = "history [--cwd] [--failed] [--since duration] [n]";
+
+pub struct History;
+
+impl Builtin for History {
+    fn name(&self) -> &'static str { "history" }
+
+    fn help(&self) -> &'static str {
+        "history [--cwd] [--failed] [--since duration] [n]\n    Display the command history with line numbers, or only its last N entries.\n    --cwd       only commands run in the current directory\n    --failed    only commands that exited with a non-zero status\n    --since D   only commands started within D, such as 30s, 15m, 1h, 2d or 1w\n    When HISTTIMEFORMAT is set and not null, it is given to strftime(3) to\n    show when each entry started."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut cwd_only, mut failed_only) = (false, false);
+        let mut since = None;
+        let mut count = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let duration = match arg.as_str() {
+                "--cwd" => {
+                    cwd_only = true;
+                    continue;
+                }
+                "--failed" => {
+                    failed_only = true;
+                    continue;
+                }
+                "--since" => match args.next() {
+                    Some(duration) => duration.as_str(),
+                    None => {
+                        let _ = writeln!(io.stderr, "history: --since: option requires an argument");
+                        let _ = writeln!(io.stderr, "history: usage: {}", USAGE);
+                        return 2;
+                    }
+                },
+                _ if arg.starts_with("--since=") => &arg["--since=".len()..],
+                "--" => {
+                    count = args.next();
+                    break;
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    let _ = writeln!(io.stderr, "history: {}: invalid option", arg);
+                    let _ = writeln!(io.stderr, "history: usage: {}", USAGE);
+                    return 2;
+                }
+                _ => {
+                    count = Some(arg);
+                    continue;
+                }
+            };
+            match parse_duration(duration) {
+                Some(seconds) => since = Some(history::now().saturating_sub(seconds)),
+                None => {
+                    let _ = writeln!(io.stderr, "history: {}: invalid duration", duration);
+                    return 1;
+                }
+            }
+        }
+        let count = match count {
+            None => None,
+            Some(n) => match n.parse::<usize>() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    let _ = writeln!(io.stderr, "history: {}: numeric argument required", n);
+                    return 1;
+                }
+            },
+        };
+
+        let cwd = logical_cwd().to_string_lossy().into_owned();
+        let entries = history::entries();
+        let shown: Vec<(usize, &history::Entry)> = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (i + 1, entry))
+            .filter(|(_, entry)| !cwd_only || entry.cwd.as_deref() == Some(cwd.as_str()))
+            .filter(|(_, entry)| !failed_only || entry.status.is_some_and(|status| status != 0))
+            .filter(|(_, entry)| since.map_or(true, |since| entry.start.is_some_and(|start| start >= since)))
+            .collect();
+        let skip = shown.len().saturating_sub(count.unwrap_or(shown.len()));
+        let time_format = vars::get("HISTTIMEFORMAT").filter(|format| !format.is_empty());
+        for (number, entry) in &shown[skip..] {
+            let time = match (&time_format, entry.start) {
+                (Some(format), Some(start)) => format_time(start, format),
+                // Like bash, entries without a timestamp show `??`.
+                (Some(_), None) => "??".to_string(),
+                (None, _) => String::new(),
+            };
+            let _ = writeln!(io.stdout, "{:5}  {}{}", number, time, entry.line);
+        }
+        0
+    }
+}
+
+/// Parses `90`, `30s`, `15m`, `1h`, `2d` or `1w` into seconds.
+fn parse_duration(text: &str) -> Option<u64> {
+    let digits_end = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    let amount: u64 = text[..digits_end].parse().ok()?;
+    let unit = match &text[digits_end..] {
+        "" | "s" => 1,
+        "m" => 60,
+        "h" => 3_600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return None,
+    };
+    amount.checked_mul(unit)
+}
+
+/// Formats `seconds` since the epoch as local time with strftime(3).
+fn format_time(seconds: u64, format: &str) -> String {
+    let Ok(format) = CString::new(format) else { return String::new() };
+    let time = seconds as libc::time_t;
+    // SAFETY: an all-zero `tm` is a valid value for localtime_r to overwrite.
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    // SAFETY: both pointers refer to live locals of the right types.
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+        return "??".to_string();
+    }
+    let mut buf = [0u8; 512];
+    // SAFETY: strftime writes at most `buf.len()` bytes and returns how many it wrote.
+    let len = unsafe { libc::strftime(buf.as_mut_ptr().cast(), buf.len(), format.as_ptr(), &tm) };
+    String::from_utf8_lossy(&buf[..len]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_durations_with_units() {
+        assert_eq!(parse_duration("90"), Some(90));
+        assert_eq!(parse_duration("30s"), Some(30));
+        assert_eq!(parse_duration("15m"), Some(900));
+        assert_eq!(parse_duration("1h"), Some(3_600));
+        assert_eq!(parse_duration("2d"), Some(172_800));
+        assert_eq!(parse_duration("1w"), Some(604_800));
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("5y"), None);
+        assert_eq!(parse_duration("-5m"), None);
+    }
+}
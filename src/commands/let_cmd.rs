@@ -0,0 +1,33 @@
+use std::io::Write;
+
+use crate::commands::{Builtin, Io};
+use crate::arith;
+
+pub struct Let;
+
+impl Builtin for Let {
+    fn name(&self) -> &'static str { "let" }
+
+    fn help(&self) -> &'static str {
+        "let arg [arg ...]\n    Evaluate each ARG as an arithmetic expression. Exits with 0 if the last\n    ARG evaluates to a non-zero value, and 1 otherwise."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        if args.is_empty() {
+            let _ = writeln!(io.stderr, "let: expression expected");
+            return 1;
+        }
+        let mut last = 0;
+        for arg in args {
+            // The shell has already expanded the arguments, so they are evaluated as they are.
+            match arith::evaluate(arg) {
+                Ok(value) => last = value,
+                Err(message) => {
+                    let _ = writeln!(io.stderr, "let: {}", message);
+                    return 1;
+                }
+            }
+        }
+        (last == 0) as i32
+    }
+}
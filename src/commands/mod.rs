@@ -1,6 +1,104 @@
+pub mod bind;
 pub mod cd;
+pub mod command;
+pub mod declare;
+pub mod dirs;
 pub mod echo;
 pub mod execute;
+pub mod hash;
+pub mod help;
+pub mod history;
+pub mod let_cmd;
 pub mod type_cmd;
+pub mod unset;
+pub mod printf;
 pub mod pwd;
+pub mod read;
+pub mod set;
+pub mod shopt;
 pub mod exit;
+pub mod test;
+pub mod trap;
+pub mod z;
+
+use std::io::{self, Read, Write};
+
+use nix::libc;
+
+use crate::shell::stdin_redirected;
+
+/// The streams a builtin uses. Redirections are applied to the underlying file
+/// descriptors, so these normally just wrap the process's own standard streams.
+pub struct Io {
+    pub stdin: Box<dyn Read>,
+    pub stdout: Box<dyn Write>,
+    pub stderr: Box<dyn Write>,
+}
+
+impl Io {
+    pub fn std() -> Self {
+        // Normally reads share std's stdin buffer with the line editor, which uses it
+        // for non-terminal input. Under a `<` redirection that buffer still belongs to
+        // the shell's own input, so the descriptor is read directly instead.
+        let stdin: Box<dyn Read> = if stdin_redirected() { Box::new(RawStdin) } else { Box::new(io::stdin()) };
+        Io { stdin, stdout: Box::new(io::stdout()), stderr: Box::new(io::stderr()) }
+    }
+}
+
+/// Unbuffered reads from fd 0.
+pub struct RawStdin;
+
+impl Read for RawStdin {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        nix::unistd::read(libc::STDIN_FILENO, buf).map_err(io::Error::from)
+    }
+}
+
+/// A command implemented inside the shell process.
+pub trait Builtin: Sync {
+    fn name(&self) -> &'static str;
+    /// Usage synopsis on the first line, followed by a longer description.
+    fn help(&self) -> &'static str;
+    /// Runs the builtin with the arguments after its name, returning the exit status.
+    fn run(&self, args: &[String], io: &mut Io) -> i32;
+}
+
+/// Every builtin the shell implements; dispatch, `type`, `help` and completion all read this.
+static BUILTINS: &[&dyn Builtin] = &[
+    &test::Bracket,
+    &bind::Bind,
+    &cd::Cd,
+    &command::CommandBuiltin,
+    &declare::Declare,
+    &dirs::Dirs,
+    &echo::Echo,
+    &exit::Exit,
+    &hash::Hash,
+    &help::Help,
+    &history::History,
+    &let_cmd::Let,
+    &exit::Logout,
+    &dirs::Popd,
+    &dirs::Pushd,
+    &printf::Printf,
+    &pwd::Pwd,
+    &read::Read,
+    &set::Set,
+    &shopt::Shopt,
+    &test::Test,
+    &trap::Trap,
+    &type_cmd::Type,
+    &unset::Unset,
+    &z::Z,
+];
+
+pub fn lookup(name: &str) -> Option<&'static dyn Builtin> {
+    BUILTINS.iter().copied().find(|builtin| builtin.name() == name)
+}
+
+pub fn builtins() -> impl Iterator<Item = &'static dyn Builtin> {
+    BUILTINS.iter().copied()
+}
+
+/// Reserved words the parser understands, reported by `type` as keywords.
+pub const KEYWORDS: &[&str] = &["!", "[[", "]]"];
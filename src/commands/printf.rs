@@ -0,0 +1,417 @@
+use std::io::Write;
+
+use crate::commands::echo::interpret_escapes;
+use crate::commands::{Builtin, Io};
+
+pub struct Printf;
+
+impl Builtin for Printf {
+    fn name(&self) -> &'static str { "printf" }
+
+    fn help(&self) -> &'static str {
+        "printf format [arguments ...]\n    Write ARGUMENTS formatted under the control of FORMAT. Besides the usual\n    %s %c %d %i %u %o %x %X %f %e %g conversions with flags, width and precision,\n    %b expands backslash escapes in its argument and %q shell-quotes it.\n    The format is reused until every argument has been consumed."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let args = match args.first().map(String::as_str) {
+            Some("--") => &args[1..],
+            _ => args,
+        };
+        let Some((format, mut rest)) = args.split_first() else {
+            let _ = writeln!(io.stderr, "printf: usage: printf format [arguments]");
+            return 2;
+        };
+        let mut formatter = Formatter { out: Vec::new(), status: 0, stopped: false };
+        loop {
+            let consumed = formatter.format_once(format, &mut rest, io);
+            if formatter.stopped || rest.is_empty() || consumed == 0 {
+                break;
+            }
+        }
+        let _ = io.stdout.write_all(&formatter.out);
+        formatter.status
+    }
+}
+
+struct Formatter {
+    out: Vec<u8>,
+    status: i32,
+    /// Set by `\c` inside a `%b` argument, which ends all output.
+    stopped: bool,
+}
+
+/// One `%` conversion: flags, width, precision and the conversion character.
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    zero: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Formatter {
+    /// Runs through the format once, returning how many arguments were consumed.
+    fn format_once(&mut self, format: &str, args: &mut &[String], io: &mut Io) -> usize {
+        let start = args.len();
+        let mut chars = format.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => {
+                    // Hand the escape (and any digits) to the shared escape decoder.
+                    let mut escape = String::from('\\');
+                    // Unlike in %b arguments, `\c` has no special meaning in the format itself.
+                    if chars.peek() == Some(&'c') {
+                        escape.push('\\');
+                    }
+                    if let Some(next) = chars.next() {
+                        escape.push(next);
+                        let (radix, max) = match next {
+                            'x' => (16, 2),
+                            '0'..='7' => (8, 2),
+                            _ => (10, 0),
+                        };
+                        while escape.len() < 2 + max && chars.peek().is_some_and(|d| d.is_digit(radix)) {
+                            escape.push(chars.next().unwrap_or_default());
+                        }
+                    }
+                    self.out.extend(interpret_escapes(&escape, false).0);
+                }
+                '%' => {
+                    if chars.peek() == Some(&'%') {
+                        chars.next();
+                        self.out.push(b'%');
+                        continue;
+                    }
+                    let mut spec = Spec::default();
+                    while let Some(&flag) = chars.peek() {
+                        match flag {
+                            '-' => spec.left = true,
+                            '+' => spec.plus = true,
+                            ' ' => spec.space = true,
+                            '0' => spec.zero = true,
+                            '#' => spec.alternate = true,
+                            _ => break,
+                        }
+                        chars.next();
+                    }
+                    if chars.peek() == Some(&'*') {
+                        chars.next();
+                        let width = self.next_integer(args, io);
+                        spec.left |= width < 0;
+                        spec.width = width.unsigned_abs() as usize;
+                    } else {
+                        spec.width = read_number(&mut chars);
+                    }
+                    if chars.peek() == Some(&'.') {
+                        chars.next();
+                        spec.precision = Some(if chars.peek() == Some(&'*') {
+                            chars.next();
+                            self.next_integer(args, io).max(0) as usize
+                        } else {
+                            read_number(&mut chars)
+                        });
+                    }
+                    let Some(conversion) = chars.next() else {
+                        let _ = writeln!(io.stderr, "printf: `%': missing format character");
+                        self.status = 1;
+                        break;
+                    };
+                    self.convert(conversion, &spec, args, io);
+                    if self.stopped {
+                        break;
+                    }
+                }
+                _ => self.out.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+            }
+        }
+        start - args.len()
+    }
+
+    fn convert(&mut self, conversion: char, spec: &Spec, args: &mut &[String], io: &mut Io) {
+        let formatted = match conversion {
+            's' => {
+                let arg = next_arg(args);
+                truncate(&arg, spec.precision)
+            }
+            'b' => {
+                let (expanded, stop) = interpret_escapes(&next_arg(args), true);
+                self.stopped = stop;
+                match String::from_utf8(expanded) {
+                    Ok(text) => truncate(&text, spec.precision),
+                    // Bytes from `\xHH` or octal escapes are cut and padded as bytes.
+                    Err(raw) => {
+                        let mut raw = raw.into_bytes();
+                        raw.truncate(spec.precision.unwrap_or(raw.len()));
+                        let padding = vec![b' '; spec.width.saturating_sub(raw.len())];
+                        let (first, second) = if spec.left { (raw, padding) } else { (padding, raw) };
+                        self.out.extend(first);
+                        self.out.extend(second);
+                        return;
+                    }
+                }
+            }
+            'q' => shell_quote(&next_arg(args)),
+            'c' => next_arg(args).chars().next().map(String::from).unwrap_or_default(),
+            'd' | 'i' => {
+                let n = self.next_integer(args, io);
+                let sign = if n < 0 {
+                    "-"
+                } else if spec.plus {
+                    "+"
+                } else if spec.space {
+                    " "
+                } else {
+                    ""
+                };
+                return self.pad_number(sign, &n.unsigned_abs().to_string(), "", spec);
+            }
+            'u' | 'o' | 'x' | 'X' => {
+                let n = self.next_integer(args, io) as u64;
+                let (digits, prefix) = match conversion {
+                    'o' => (format!("{:o}", n), if spec.alternate && n != 0 { "0" } else { "" }),
+                    'x' => (format!("{:x}", n), if spec.alternate && n != 0 { "0x" } else { "" }),
+                    'X' => (format!("{:X}", n), if spec.alternate && n != 0 { "0X" } else { "" }),
+                    _ => (n.to_string(), ""),
+                };
+                return self.pad_number("", &digits, prefix, spec);
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let arg = next_arg(args);
+                let value = match arg.trim().parse::<f64>() {
+                    Ok(value) => value,
+                    Err(_) if arg.is_empty() => 0.0,
+                    Err(_) => {
+                        let _ = writeln!(io.stderr, "printf: {}: invalid number", arg);
+                        self.status = 1;
+                        0.0
+                    }
+                };
+                let sign = if value.is_sign_negative() && value != 0.0 {
+                    "-"
+                } else if spec.plus {
+                    "+"
+                } else if spec.space {
+                    " "
+                } else {
+                    ""
+                };
+                let body = format_float(value.abs(), conversion, spec);
+                return self.pad_number(sign, &body, "", &Spec { precision: None, ..*spec });
+            }
+            other => {
+                let _ = writeln!(io.stderr, "printf: `{}': invalid format character", other);
+                self.status = 1;
+                self.stopped = true;
+                return;
+            }
+        };
+        self.out.extend_from_slice(pad(&formatted, spec.width, spec.left, ' ').as_bytes());
+    }
+
+    /// Lays out a number: precision sets the minimum digit count, then the
+    /// sign and prefix are attached and the result padded to the field width.
+    fn pad_number(&mut self, sign: &str, digits: &str, prefix: &str, spec: &Spec) {
+        let digits = match spec.precision {
+            Some(p) if p > digits.len() => format!("{}{}", "0".repeat(p - digits.len()), digits),
+            _ => digits.to_string(),
+        };
+        let head = format!("{}{}", sign, prefix);
+        let formatted = if spec.zero && !spec.left && spec.precision.is_none() {
+            let fill = spec.width.saturating_sub(head.len() + digits.len());
+            format!("{}{}{}", head, "0".repeat(fill), digits)
+        } else {
+            pad(&format!("{}{}", head, digits), spec.width, spec.left, ' ')
+        };
+        self.out.extend_from_slice(formatted.as_bytes());
+    }
+
+    /// Consumes the next argument as an integer. Like C, `'x` gives the character code.
+    /// An argument with trailing junk is reported, but its numeric prefix is still used.
+    fn next_integer(&mut self, args: &mut &[String], io: &mut Io) -> i64 {
+        let arg = next_arg(args);
+        let text = arg.trim();
+        if text.is_empty() {
+            return 0;
+        }
+        if let Some(quoted) = text.strip_prefix(['\'', '"']) {
+            return quoted.chars().next().map_or(0, |c| c as i64);
+        }
+        let (negative, digits) = match text.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (radix, digits) = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+            (16, hex)
+        } else if digits.len() > 1 && digits.starts_with('0') {
+            (8, &digits[1..])
+        } else {
+            (10, digits)
+        };
+        let end = digits.find(|c: char| !c.is_digit(radix)).unwrap_or(digits.len());
+        // The magnitude of i64::MIN is one more than i64::MAX, so parse it unsigned.
+        let magnitude = if end == 0 { Ok(0) } else { u64::from_str_radix(&digits[..end], radix) };
+        let limit = if negative { i64::MIN.unsigned_abs() } else { i64::MAX as u64 };
+        let n = match magnitude {
+            Ok(m) if m <= limit && negative => (m as i64).wrapping_neg(),
+            Ok(m) if m <= limit => m as i64,
+            _ => {
+                let _ = writeln!(io.stderr, "printf: {}: Result too large", arg);
+                self.status = 1;
+                return if negative { i64::MIN } else { i64::MAX };
+            }
+        };
+        if end == 0 || end < digits.len() {
+            let _ = writeln!(io.stderr, "printf: {}: invalid number", arg);
+            self.status = 1;
+        }
+        n
+    }
+}
+
+fn next_arg(args: &mut &[String]) -> String {
+    match args.split_first() {
+        Some((first, rest)) => {
+            *args = rest;
+            first.clone()
+        }
+        None => String::new(),
+    }
+}
+
+fn read_number(chars: &mut std::iter::Peekable<std::str::Chars>) -> usize {
+    let mut n = 0;
+    while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+        n = n * 10 + d as usize;
+        chars.next();
+    }
+    n
+}
+
+fn truncate(s: &str, precision: Option<usize>) -> String {
+    match precision {
+        Some(p) => s.chars().take(p).collect(),
+        None => s.to_string(),
+    }
+}
+
+fn pad(s: &str, width: usize, left: bool, fill: char) -> String {
+    let len = s.chars().count();
+    if len >= width {
+        return s.to_string();
+    }
+    let padding: String = std::iter::repeat(fill).take(width - len).collect();
+    if left {
+        format!("{}{}", s, padding)
+    } else {
+        format!("{}{}", padding, s)
+    }
+}
+
+/// Formats a non-negative float the way C's %f, %e and %g do.
+fn format_float(value: f64, conversion: char, spec: &Spec) -> String {
+    let precision = spec.precision.unwrap_or(6);
+    let formatted = match conversion.to_ascii_lowercase() {
+        'f' => format!("{:.*}", precision, value),
+        'e' => c_exponent(value, precision),
+        _ => {
+            // %g picks %e or %f based on the exponent, then drops trailing zeros.
+            let precision = precision.max(1);
+            let exponent = c_exponent(value, precision - 1)
+                .split('e')
+                .nth(1)
+                .and_then(|e| e.parse::<i32>().ok())
+                .unwrap_or(0);
+            let mut text = if exponent < -4 || exponent >= precision as i32 {
+                c_exponent(value, precision - 1)
+            } else {
+                format!("{:.*}", (precision as i32 - 1 - exponent).max(0) as usize, value)
+            };
+            if !spec.alternate && text.contains('.') {
+                let (mantissa, exp) = match text.find('e') {
+                    Some(i) => text.split_at(i),
+                    None => (text.as_str(), ""),
+                };
+                text = format!("{}{}", mantissa.trim_end_matches('0').trim_end_matches('.'), exp);
+            }
+            text
+        }
+    };
+    if conversion.is_ascii_uppercase() {
+        formatted.to_uppercase()
+    } else {
+        formatted
+    }
+}
+
+/// Rust prints `1.5e2`; C wants a signed, at least two-digit exponent: `1.500000e+02`.
+fn c_exponent(value: f64, precision: usize) -> String {
+    let text = format!("{:.*e}", precision, value);
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    format!("{}e{}{:02}", mantissa, if exponent < 0 { '-' } else { '+' }, exponent.abs())
+}
+
+/// Quotes `s` so the shell would read it back as a single word.
+pub fn shell_quote(s: &str) -> String {
+    if s.is_empty() {
+        return "''".to_string();
+    }
+    if s.chars().any(|c| c.is_control()) {
+        let mut quoted = String::from("$'");
+        for c in s.chars() {
+            match c {
+                '\n' => quoted.push_str("\\n"),
+                '\t' => quoted.push_str("\\t"),
+                '\r' => quoted.push_str("\\r"),
+                '\x1b' => quoted.push_str("\\E"),
+                '\'' => quoted.push_str("\\'"),
+                '\\' => quoted.push_str("\\\\"),
+                c if c.is_control() => quoted.push_str(&format!("\\{:03o}", c as u32)),
+                c => quoted.push(c),
+            }
+        }
+        quoted.push('\'');
+        return quoted;
+    }
+    let mut quoted = String::new();
+    for c in s.chars() {
+        if !(c.is_alphanumeric() || "_@%+=:,./-".contains(c)) {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn format(format: &str, args: &[&str]) -> (String, i32) {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let mut io = Io { stdin: Box::new(io::empty()), stdout: Box::new(io::sink()), stderr: Box::new(io::sink()) };
+        let mut formatter = Formatter { out: Vec::new(), status: 0, stopped: false };
+        formatter.format_once(format, &mut args.as_slice(), &mut io);
+        (String::from_utf8_lossy(&formatter.out).into_owned(), formatter.status)
+    }
+
+    #[test]
+    fn uses_the_numeric_prefix_of_invalid_integers() {
+        assert_eq!(format("%d", &["12abc"]), ("12".to_string(), 1));
+        assert_eq!(format("%x", &["0x1fz"]), ("1f".to_string(), 1));
+        assert_eq!(format("%d", &["abc"]), ("0".to_string(), 1));
+        assert_eq!(format("%d %o %d", &["0x10", "010", "'A"]), ("16 10 65".to_string(), 0));
+    }
+
+    #[test]
+    fn covers_the_whole_i64_range() {
+        assert_eq!(format("%d", &["-9223372036854775808"]), ("-9223372036854775808".to_string(), 0));
+        assert_eq!(format("%d", &["9223372036854775807"]), ("9223372036854775807".to_string(), 0));
+        assert_eq!(format("%d", &["9223372036854775808"]), ("9223372036854775807".to_string(), 1));
+        assert_eq!(format("%d", &["-9223372036854775809"]), ("-9223372036854775808".to_string(), 1));
+    }
+}
@@ -1,13 +1,44 @@
 use std::env;
+use std::io::Write;
 
-pub fn handle_pwd_command(input: &str) -> bool {
-    // Check if the command is exactly "pwd"
-    if input.trim() == "pwd" {
+use crate::commands::cd::logical_cwd;
+use crate::commands::{Builtin, Io};
+
+pub struct Pwd;
+
+impl Builtin for Pwd {
+    fn name(&self) -> &'static str { "pwd" }
+
+    fn help(&self) -> &'static str {
+        "pwd [-L|-P]\n    Print the name of the current working directory.\n    -L  print $PWD if it names the current directory (default)\n    -P  print the physical directory, with all symbolic links resolved"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let mut physical = false;
+        for arg in args {
+            match arg.as_str() {
+                "-L" => physical = false,
+                "-P" => physical = true,
+                _ => {
+                    let _ = writeln!(io.stderr, "pwd: {}: invalid option", arg);
+                    let _ = writeln!(io.stderr, "pwd: usage: pwd [-LP]");
+                    return 2;
+                }
+            }
+        }
+        if !physical {
+            let _ = writeln!(io.stdout, "{}", logical_cwd().display());
+            return 0;
+        }
         match env::current_dir() {
-            Ok(path) => println!("{}", path.display()),
-            Err(e) => eprintln!("pwd: error: {}", e),
+            Ok(path) => {
+                let _ = writeln!(io.stdout, "{}", path.display());
+                0
+            }
+            Err(e) => {
+                let _ = writeln!(io.stderr, "pwd: error: {}", e);
+                1
+            }
         }
-        return true;
     }
-    false
-}
\ No newline at end of file
+}
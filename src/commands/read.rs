@@ -0,0 +1,324 @@
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::libc;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::termios::{self, LocalFlags, SetArg, SpecialCharacterIndices, Termios};
+use nix::unistd::isatty;
+
+use crate::commands::{Builtin, Io, RawStdin};
+use crate::shell::stdin_redirected;
+use crate::util::{ifs, split_fields};
+use crate::vars;
+
+/// Status `read` returns when `-t` expires, as bash does (128 + SIGALRM).
+const TIMEOUT_STATUS: i32 = 142;
+
+#[derive(Default)]
+struct Options {
+    raw: bool,
+    silent: bool,
+    array: Option<String>,
+    delimiter: Option<char>,
+    count: Option<usize>,
+    prompt: Option<String>,
+    timeout: Option<f64>,
+}
+
+pub struct Read;
+
+impl Builtin for Read {
+    fn name(&self) -> &'static str { "read" }
+
+    fn help(&self) -> &'static str {
+        "read [-rs] [-a array] [-d delim] [-n nchars] [-p prompt] [-t timeout] [name ...]\n    Read a line from standard input and split it into fields using IFS. The\n    first field goes to the first NAME, and so on, with the remainder of the\n    line assigned to the last NAME. Without NAMEs the line is stored in REPLY.\n    -r  do not treat backslashes as escape characters\n    -s  do not echo input coming from a terminal\n    -a  assign the fields to the indexed array ARRAY\n    -d  stop at the first character of DELIM rather than newline\n    -n  return after reading NCHARS characters\n    -p  print PROMPT on standard error when reading from a terminal\n    -t  time out after TIMEOUT seconds and return a status above 128"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (options, names) = match parse_options(args) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                let _ = writeln!(io.stderr, "read: {}", message);
+                let _ = writeln!(io.stderr, "read: usage: read [-rs] [-a array] [-d delim] [-n nchars] [-p prompt] [-t timeout] [name ...]");
+                return 2;
+            }
+        };
+        if let Some(name) = names.iter().chain(options.array.iter()).find(|n| !vars::is_valid_name(n)) {
+            let _ = writeln!(io.stderr, "read: `{}': not a valid identifier", name);
+            return 1;
+        }
+
+        let interactive = isatty(libc::STDIN_FILENO).unwrap_or(false);
+        if let (Some(prompt), true) = (&options.prompt, interactive) {
+            let _ = write!(io.stderr, "{}", prompt);
+            let _ = io.stderr.flush();
+        }
+        // The line editor reads a terminal directly, so reading it through std's
+        // buffer could swallow typed-ahead input. Otherwise, unless redirected,
+        // input comes through the buffer the editor shares.
+        let buffered = !interactive && !stdin_redirected();
+        if options.timeout == Some(0.0) {
+            // `-t 0` only reports whether input is available.
+            return if wait_readable(Some(Instant::now()), buffered) { 0 } else { 1 };
+        }
+
+        let _terminal = interactive.then(|| TerminalGuard::new(&options)).flatten();
+        let (text, outcome) = if interactive {
+            read_input(&mut RawStdin, &options, buffered)
+        } else {
+            read_input(&mut io.stdin, &options, buffered)
+        };
+        assign(&text, &names, &options);
+        match outcome {
+            Outcome::Delimited => 0,
+            Outcome::Eof => 1,
+            Outcome::TimedOut => TIMEOUT_STATUS,
+        }
+    }
+}
+
+fn parse_options(args: &[String]) -> Result<(Options, Vec<String>), String> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+    let mut names = Vec::new();
+    while let Some(arg) = args.next() {
+        let Some(flags) = arg.strip_prefix('-').filter(|f| names.is_empty() && !f.is_empty()) else {
+            names.push(arg.clone());
+            continue;
+        };
+        if flags == "-" {
+            names.extend(args.by_ref().cloned());
+            break;
+        }
+        for (i, flag) in flags.char_indices() {
+            match flag {
+                'r' => options.raw = true,
+                's' => options.silent = true,
+                'a' | 'd' | 'n' | 'p' | 't' => {
+                    // The value is either the rest of this word or the next argument.
+                    let attached = &flags[i + 1..];
+                    let value = if attached.is_empty() {
+                        args.next().cloned().ok_or_else(|| format!("-{}: option requires an argument", flag))?
+                    } else {
+                        attached.to_string()
+                    };
+                    match flag {
+                        'a' => options.array = Some(value),
+                        'd' => options.delimiter = Some(value.chars().next().unwrap_or('\0')),
+                        'n' => {
+                            options.count = Some(value.parse().map_err(|_| format!("{}: invalid number", value))?)
+                        }
+                        'p' => options.prompt = Some(value),
+                        _ => {
+                            let seconds: f64 = value
+                                .parse()
+                                .ok()
+                                .filter(|s: &f64| *s >= 0.0)
+                                .ok_or_else(|| format!("{}: invalid timeout specification", value))?;
+                            options.timeout = Some(seconds);
+                        }
+                    }
+                    break;
+                }
+                _ => return Err(format!("-{}: invalid option", flag)),
+            }
+        }
+    }
+    Ok((options, names))
+}
+
+enum Outcome {
+    Delimited,
+    Eof,
+    TimedOut,
+}
+
+/// Reads characters up to the delimiter, returning each with a flag marking it
+/// as backslash-escaped (and therefore never a field separator).
+fn read_input(input: &mut dyn io::Read, options: &Options, buffered: bool) -> (Vec<(char, bool)>, Outcome) {
+    let delimiter = options.delimiter.unwrap_or('\n');
+    let deadline = options.timeout.map(|t| Instant::now() + Duration::from_secs_f64(t));
+    let mut text = Vec::new();
+    loop {
+        if options.count.is_some_and(|n| text.len() >= n) {
+            return (text, Outcome::Delimited);
+        }
+        if deadline.is_some() && !wait_readable(deadline, buffered) {
+            return (text, Outcome::TimedOut);
+        }
+        let Some(c) = read_char(input) else { return (text, Outcome::Eof) };
+        if c == '\\' && !options.raw {
+            match read_char(input) {
+                // Backslash-newline continues the line.
+                Some('\n') => continue,
+                Some(escaped) => text.push((escaped, true)),
+                None => return (text, Outcome::Eof),
+            }
+            continue;
+        }
+        if c == delimiter {
+            return (text, Outcome::Delimited);
+        }
+        text.push((c, false));
+    }
+}
+
+/// Reads one UTF-8 character a byte at a time so nothing past the delimiter is consumed.
+fn read_char(input: &mut dyn io::Read) -> Option<char> {
+    let mut byte = [0u8; 1];
+    input.read_exact(&mut byte).ok()?;
+    let width = match byte[0] {
+        0x00..=0x7f => return Some(byte[0] as char),
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => return Some(char::REPLACEMENT_CHARACTER),
+    };
+    let mut buf = vec![byte[0]];
+    for _ in 1..width {
+        input.read_exact(&mut byte).ok()?;
+        buf.push(byte[0]);
+    }
+    Some(std::str::from_utf8(&buf).ok().and_then(|s| s.chars().next()).unwrap_or(char::REPLACEMENT_CHARACTER))
+}
+
+/// Waits until stdin has data or `deadline` passes; no deadline means ready.
+/// With `buffered`, data already in std's stdin buffer counts too.
+fn wait_readable(deadline: Option<Instant>, buffered: bool) -> bool {
+    let Some(deadline) = deadline else { return true };
+    if buffered && buffered_input() {
+        return true;
+    }
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let mut fds = [PollFd::new(libc::STDIN_FILENO, PollFlags::POLLIN)];
+    matches!(poll(&mut fds, remaining.as_millis() as libc::c_int), Ok(n) if n > 0)
+}
+
+/// Whether std's stdin buffer holds data, which `poll` cannot see. The buffer
+/// is filled, if it is empty, without blocking.
+fn buffered_input() -> bool {
+    let Ok(flags) = fcntl(libc::STDIN_FILENO, FcntlArg::F_GETFL) else { return false };
+    let flags = OFlag::from_bits_truncate(flags);
+    if fcntl(libc::STDIN_FILENO, FcntlArg::F_SETFL(flags | OFlag::O_NONBLOCK)).is_err() {
+        return false;
+    }
+    let ready = io::stdin().lock().fill_buf().is_ok_and(|data| !data.is_empty());
+    let _ = fcntl(libc::STDIN_FILENO, FcntlArg::F_SETFL(flags));
+    ready
+}
+
+fn assign(text: &[(char, bool)], names: &[String], options: &Options) {
+    let collect = |span: &[(char, bool)]| span.iter().map(|(c, _)| *c).collect::<String>();
+    let ifs = ifs();
+    if let Some(array) = &options.array {
+        let fields = split_fields(text, &ifs).into_iter().map(|(s, e)| collect(&text[s..e])).collect();
+        vars::set_array(array, fields);
+        return;
+    }
+    if names.is_empty() {
+        vars::set("REPLY", &collect(text));
+        return;
+    }
+    let fields = split_fields(text, &ifs);
+    for (i, name) in names.iter().enumerate() {
+        let value = match fields.get(i) {
+            // The last name takes the rest of the line, minus trailing IFS whitespace.
+            Some(&(start, _)) if i == names.len() - 1 => {
+                let mut end = text.len();
+                while end > start && !text[end - 1].1 && text[end - 1].0.is_whitespace() && ifs.contains(text[end - 1].0) {
+                    end -= 1;
+                }
+                collect(&text[start..end])
+            }
+            Some(&(start, end)) => collect(&text[start..end]),
+            None => String::new(),
+        };
+        vars::set(name, &value);
+    }
+}
+
+/// Switches the terminal into the mode `-s`/`-n`/`-d` need and restores it on drop.
+struct TerminalGuard {
+    original: Termios,
+}
+
+impl TerminalGuard {
+    fn new(options: &Options) -> Option<Self> {
+        let needs_raw = options.count.is_some() || options.delimiter.is_some();
+        if !options.silent && !needs_raw {
+            return None;
+        }
+        let original = termios::tcgetattr(libc::STDIN_FILENO).ok()?;
+        let mut modified = original.clone();
+        if options.silent {
+            modified.local_flags.remove(LocalFlags::ECHO);
+        }
+        if needs_raw {
+            // Without canonical mode, characters arrive as they are typed.
+            modified.local_flags.remove(LocalFlags::ICANON);
+            modified.control_chars[SpecialCharacterIndices::VMIN as usize] = 1;
+            modified.control_chars[SpecialCharacterIndices::VTIME as usize] = 0;
+        }
+        termios::tcsetattr(libc::STDIN_FILENO, SetArg::TCSANOW, &modified).ok()?;
+        Some(TerminalGuard { original })
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(libc::STDIN_FILENO, SetArg::TCSANOW, &self.original);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|a| a.to_string()).collect()
+    }
+
+    fn read(input: &str, options: &Options) -> (String, Outcome) {
+        let (text, outcome) = read_input(&mut input.as_bytes(), options, false);
+        (text.iter().map(|(c, _)| *c).collect(), outcome)
+    }
+
+    #[test]
+    fn parses_clustered_and_attached_options() {
+        let (options, names) = parse_options(&args(&["-rs", "-n3", "-d", ",", "-p", "> ", "first", "-r"])).unwrap();
+        assert!(options.raw && options.silent);
+        assert_eq!((options.count, options.delimiter, options.prompt.as_deref()), (Some(3), Some(','), Some("> ")));
+        assert_eq!(names, ["first", "-r"]);
+        assert_eq!(parse_options(&args(&["-t", "0.5", "--", "-x"])).unwrap().0.timeout, Some(0.5));
+        assert_eq!(parse_options(&args(&["-t", "0.5", "--", "-x"])).unwrap().1, ["-x"]);
+        assert_eq!(parse_options(&args(&["-q"])).err().as_deref(), Some("-q: invalid option"));
+        assert_eq!(parse_options(&args(&["-t", "-1"])).err().as_deref(), Some("-1: invalid timeout specification"));
+        assert_eq!(parse_options(&args(&["-a"])).err().as_deref(), Some("-a: option requires an argument"));
+    }
+
+    #[test]
+    fn reads_up_to_the_delimiter() {
+        let options = Options::default();
+        let (text, outcome) = read("one \\\ntwo\\ three\nrest", &options);
+        assert_eq!(text, "one two three");
+        assert!(matches!(outcome, Outcome::Delimited));
+        assert!(matches!(read("no newline", &options), (text, Outcome::Eof) if text == "no newline"));
+
+        let raw = Options { raw: true, delimiter: Some(','), ..Options::default() };
+        assert_eq!(read("a\\b,c", &raw).0, "a\\b");
+        let counted = Options { count: Some(2), ..Options::default() };
+        assert_eq!(read("héllo", &counted).0, "hé");
+    }
+
+    #[test]
+    fn assigns_fields_with_the_rest_to_the_last_name() {
+        let text: Vec<(char, bool)> = "  a b c  d  ".chars().map(|c| (c, false)).collect();
+        assign(&text, &args(&["read_test_first", "read_test_rest"]), &Options::default());
+        assert_eq!(vars::get("read_test_first").as_deref(), Some("a"));
+        assert_eq!(vars::get("read_test_rest").as_deref(), Some("b c  d"));
+        assign(&text, &args(&["read_test_one", "read_test_two", "read_test_three", "read_test_four", "read_test_five"]), &Options::default());
+        assert_eq!(vars::get("read_test_four").as_deref(), Some("d"));
+        assert_eq!(vars::get("read_test_five").as_deref(), Some(""));
+    }
+}
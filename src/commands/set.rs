@@ -0,0 +1,215 @@
+use std::env;
+use std::io::Write;
+
+use rustyline::EditMode;
+
+use crate::commands::declare::quote;
+use crate::commands::shopt::ShellOption;
+use crate::commands::{Builtin, Io};
+use crate::keybindings;
+use crate::shell::is_interactive;
+use crate::util::single_quote;
+use crate::vars::{self, Value};
+
+const USAGE: &str = "set [-efnuvxC] [-o option-name] [--] [-] [arg ...]";
+
+/// Options set by name with `set -o`, most of them also by a single letter.
+static OPTIONS: [ShellOption; 10] = [
+    // Line editing with emacs-style keys; turning it off selects vi.
+    ShellOption::new("emacs", true),
+    // Exit as soon as a command fails, outside conditions.
+    ShellOption::new("errexit", false),
+    // `>` refuses to overwrite an existing file; `>|` still does.
+    ShellOption::new("noclobber", false),
+    // Read commands and check their syntax without running them.
+    ShellOption::new("noexec", false),
+    // Disable pathname expansion. Accepted and reported, but there is no
+    // pathname expansion for it to disable yet.
+    ShellOption::new("noglob", false),
+    // Expanding an unset parameter is an error.
+    ShellOption::new("nounset", false),
+    // A pipeline fails if any of its commands does, not just the last.
+    // Accepted and reported, but it has no effect until pipelines exist.
+    ShellOption::new("pipefail", false),
+    // Echo input lines to stderr as they are read.
+    ShellOption::new("verbose", false),
+    // Line editing with vi-style insert and command modes.
+    ShellOption::new("vi", false),
+    // Print each command, expanded and prefixed by PS4, before running it.
+    ShellOption::new("xtrace", false),
+];
+
+/// The single-letter flags, in the order `$-` lists them.
+const FLAGS: &[(char, &str)] = &[
+    ('e', "errexit"),
+    ('f', "noglob"),
+    ('n', "noexec"),
+    ('u', "nounset"),
+    ('v', "verbose"),
+    ('x', "xtrace"),
+    ('C', "noclobber"),
+];
+
+pub fn find_option(name: &str) -> Option<&'static ShellOption> {
+    OPTIONS.iter().find(|option| option.name == name)
+}
+
+pub fn options() -> &'static [ShellOption] {
+    &OPTIONS
+}
+
+pub fn is_enabled(name: &str) -> bool {
+    find_option(name).is_some_and(ShellOption::is_enabled)
+}
+
+/// The value of `$-`: the letters of the flags that are on, with `i` for an
+/// interactive shell and `s` because commands are read from standard input.
+pub fn flags() -> String {
+    let mut flags: String = FLAGS.iter().filter(|(_, name)| is_enabled(name)).map(|(flag, _)| *flag).collect();
+    if is_interactive() {
+        let at = flags.find(|flag| !matches!(flag, 'e' | 'f')).unwrap_or(flags.len());
+        flags.insert(at, 'i');
+    }
+    flags.push('s');
+    flags
+}
+
+pub struct Set;
+
+impl Builtin for Set {
+    fn name(&self) -> &'static str { "set" }
+
+    fn help(&self) -> &'static str {
+        "set [-efnuvxC] [-o option-name] [--] [-] [arg ...]\n    Set or unset shell options and positional parameters. Without arguments,\n    display all variables. `-X' turns flag X on and `+X' turns it off.\n    -e  exit when a command fails, unless it is tested by `&&', `||' or `!'\n    -f  disable pathname expansion (no effect yet: none is done)\n    -n  read commands without running them (ignored by interactive shells)\n    -u  treat expanding an unset parameter as an error\n    -v  print input lines as they are read\n    -x  print commands and their arguments, after PS4, as they are run\n    -C  do not let `>' overwrite existing files\n    -o name  enable option NAME: one of the above by its long name, pipefail\n             (no effect until pipelines exist), emacs or vi; -o alone reports every option, +o alone prints\n             commands that restore them\n    --  assign the remaining arguments to the positional parameters, or\n        unset them if there are none\n    -   turn off -v and -x and assign any remaining arguments"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        if args.is_empty() {
+            list_variables(io);
+            return 0;
+        }
+        let mut params = None;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let (enable, flags) = match arg.as_str() {
+                "--" => {
+                    params = Some(args.by_ref().cloned().collect());
+                    break;
+                }
+                "-" => {
+                    set_option("verbose", false);
+                    set_option("xtrace", false);
+                    let rest: Vec<String> = args.by_ref().cloned().collect();
+                    params = (!rest.is_empty()).then_some(rest);
+                    break;
+                }
+                _ => match arg.split_at(arg.len().min(1)) {
+                    ("-", flags) => (true, flags),
+                    ("+", flags) => (false, flags),
+                    _ => {
+                        params = Some(std::iter::once(arg).chain(args.by_ref()).cloned().collect());
+                        break;
+                    }
+                },
+            };
+            for flag in flags.chars() {
+                let name = match flag {
+                    'o' => match args.next() {
+                        Some(name) if find_option(name).is_some() => name.as_str(),
+                        Some(name) => {
+                            let _ = writeln!(io.stderr, "set: {}: invalid option name", name);
+                            return 2;
+                        }
+                        None => {
+                            list_options(io, enable);
+                            continue;
+                        }
+                    },
+                    _ => match FLAGS.iter().find(|(letter, _)| *letter == flag) {
+                        Some((_, name)) => name,
+                        None => {
+                            let _ = writeln!(io.stderr, "set: {}{}: invalid option", if enable { '-' } else { '+' }, flag);
+                            let _ = writeln!(io.stderr, "set: usage: {}", USAGE);
+                            return 2;
+                        }
+                    },
+                };
+                set_option(name, enable);
+            }
+        }
+        if let Some(params) = params {
+            vars::set_positional(params);
+        }
+        0
+    }
+}
+
+pub fn set_option(name: &str, enable: bool) {
+    let Some(option) = find_option(name) else { return };
+    option.set(enable);
+    if matches!(name, "emacs" | "vi") {
+        // The editing modes exclude each other. Like bash, turning one off leaves
+        // neither on, and the editor then keeps to emacs keys.
+        if enable {
+            let other = if name == "vi" { "emacs" } else { "vi" };
+            if let Some(other) = find_option(other) {
+                other.set(false);
+            }
+        }
+        keybindings::set_mode(if is_enabled("vi") { EditMode::Vi } else { EditMode::Emacs });
+    }
+}
+
+/// `set -o` reports each option's state; `set +o` prints the commands that restore it.
+fn list_options(io: &mut Io, human: bool) {
+    for option in &OPTIONS {
+        let enabled = option.is_enabled();
+        if human {
+            let _ = writeln!(io.stdout, "{:<15}\t{}", option.name, if enabled { "on" } else { "off" });
+        } else {
+            let _ = writeln!(io.stdout, "set {}o {}", if enabled { '-' } else { '+' }, option.name);
+        }
+    }
+}
+
+/// Prints every variable, shell and environment alike, sorted by name.
+fn list_variables(io: &mut Io) {
+    let mut names = vars::shell_names();
+    names.extend(env::vars_os().filter_map(|(name, _)| name.into_string().ok()).filter(|name| vars::is_valid_name(name)));
+    names.sort();
+    names.dedup();
+    for name in names {
+        let value = match vars::value(&name) {
+            Some(Value::Scalar(value)) => single_quote(&value),
+            Some(Value::Indexed(items)) => {
+                let items: Vec<String> = items.iter().map(|(i, value)| format!("[{}]={}", i, quote(value))).collect();
+                format!("({})", items.join(" "))
+            }
+            Some(Value::Assoc(items)) => {
+                let items: String = items.iter().map(|(key, value)| format!("[{}]={} ", key, quote(value))).collect();
+                format!("({})", items)
+            }
+            None => continue,
+        };
+        let _ = writeln!(io.stdout, "{}={}", name, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_list_the_enabled_options() {
+        assert_eq!(flags(), "s");
+        for name in ["errexit", "noglob", "pipefail", "xtrace", "noclobber"] {
+            find_option(name).unwrap().set(true);
+        }
+        let enabled = flags();
+        for name in ["errexit", "noglob", "pipefail", "xtrace", "noclobber"] {
+            find_option(name).unwrap().set(false);
+        }
+        assert_eq!(enabled, "efxCs");
+        assert!(options().iter().any(|option| option.name == "pipefail"));
+    }
+}
@@ -0,0 +1,131 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::commands::{set, Builtin, Io};
+
+/// A bash-style optional behaviour toggled with `shopt -s/-u`.
+pub struct ShellOption {
+    pub name: &'static str,
+    enabled: AtomicBool,
+}
+
+impl ShellOption {
+    pub const fn new(name: &'static str, enabled: bool) -> Self {
+        ShellOption { name, enabled: AtomicBool::new(enabled) }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    pub fn set(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+}
+
+static OPTIONS: [ShellOption; 5] = [
+    // Typing a directory name on its own changes into it.
+    ShellOption::new("autocd", false),
+    // `cd NAME` with no such directory changes to the directory in variable NAME.
+    ShellOption::new("cdable_vars", false),
+    // On exit, history is appended to the history file instead of replacing it.
+    ShellOption::new("histappend", false),
+    // `[[ == ]]`, `[[ != ]]` and `[[ =~ ]]` ignore case.
+    ShellOption::new("nocasematch", false),
+    // `echo` interprets backslash escapes without `-e`.
+    ShellOption::new("xpg_echo", false),
+];
+
+pub fn find_option(name: &str) -> Option<&'static ShellOption> {
+    OPTIONS.iter().find(|option| option.name == name)
+}
+
+pub fn is_enabled(name: &str) -> bool {
+    find_option(name).is_some_and(ShellOption::is_enabled)
+}
+
+pub struct Shopt;
+
+impl Builtin for Shopt {
+    fn name(&self) -> &'static str { "shopt" }
+
+    fn help(&self) -> &'static str {
+        "shopt [-pqsu] [-o] [optname ...]\n    Set and unset shell options. Without -s or -u, report each option's state.\n    -s  enable each OPTNAME\n    -u  disable each OPTNAME\n    -o  use the options of `set -o' instead\n    -p  print in a form that can be reused as input\n    -q  suppress output; the status tells whether every OPTNAME is enabled"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut set, mut unset, mut reusable, mut quiet, mut set_options) = (false, false, false, false, false);
+        let mut names = Vec::new();
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(flags) if names.is_empty() && !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            's' => set = true,
+                            'u' => unset = true,
+                            'p' => reusable = true,
+                            'q' => quiet = true,
+                            'o' => set_options = true,
+                            _ => {
+                                let _ = writeln!(io.stderr, "shopt: -{}: invalid option", flag);
+                                let _ = writeln!(io.stderr, "shopt: usage: shopt [-pqsu] [-o] [optname ...]");
+                                return 2;
+                            }
+                        }
+                    }
+                }
+                _ => names.push(arg.as_str()),
+            }
+        }
+        if set && unset {
+            let _ = writeln!(io.stderr, "shopt: cannot set and unset shell options simultaneously");
+            return 1;
+        }
+
+        let table: &'static [ShellOption] = if set_options { set::options() } else { &OPTIONS };
+        let mut selected = Vec::new();
+        for name in &names {
+            match table.iter().find(|option| option.name == *name) {
+                Some(option) => selected.push(option),
+                None => {
+                    let kind = if set_options { "option name" } else { "shell option name" };
+                    let _ = writeln!(io.stderr, "shopt: {}: invalid {}", name, kind);
+                    return 1;
+                }
+            }
+        }
+        if set || unset {
+            for option in &selected {
+                if set_options {
+                    // Through `set`, so that choosing vi or emacs reaches the editor.
+                    set::set_option(option.name, set);
+                } else {
+                    option.set(set);
+                }
+            }
+            return 0;
+        }
+
+        if names.is_empty() {
+            selected.extend(table.iter());
+        }
+        let mut status = 0;
+        for option in selected {
+            let enabled = option.is_enabled();
+            if !enabled && !names.is_empty() {
+                status = 1;
+            }
+            if quiet {
+                continue;
+            }
+            if reusable && set_options {
+                let _ = writeln!(io.stdout, "set {}o {}", if enabled { '-' } else { '+' }, option.name);
+            } else if reusable {
+                let _ = writeln!(io.stdout, "shopt {} {}", if enabled { "-s" } else { "-u" }, option.name);
+            } else {
+                let _ = writeln!(io.stdout, "{:<15}\t{}", option.name, if enabled { "on" } else { "off" });
+            }
+        }
+        status
+    }
+}
@@ -0,0 +1,436 @@
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+use nix::unistd::{access, isatty, AccessFlags};
+
+use crate::commands::{shopt, Builtin, Io};
+use crate::pattern;
+use crate::util::{expand_word, unquoted};
+use crate::vars;
+
+pub struct Test;
+
+impl Builtin for Test {
+    fn name(&self) -> &'static str { "test" }
+
+    fn help(&self) -> &'static str {
+        "test [expr]\n    Evaluate a conditional expression. Exits with 0 if EXPR is true, 1 if\n    it is false and 2 if it is malformed.\n    File tests: -e -f -d -x -r -w -s -L, FILE1 -nt/-ot/-ef FILE2\n    Strings: -z -n STRING, S1 = S2, S1 != S2, S1 < S2, S1 > S2\n    Integers: N1 -eq/-ne/-lt/-le/-gt/-ge N2\n    Combine with ! EXPR, EXPR -a EXPR, EXPR -o EXPR and ( EXPR )."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        report(self.name(), evaluate(args), io)
+    }
+}
+
+pub struct Bracket;
+
+impl Builtin for Bracket {
+    fn name(&self) -> &'static str { "[" }
+
+    fn help(&self) -> &'static str {
+        "[ arg... ]\n    Evaluate a conditional expression. This is a synonym for the \"test\"\n    builtin, but the last argument must be a literal `]'."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        match args.split_last() {
+            Some((last, args)) if last == "]" => report(self.name(), evaluate(args), io),
+            _ => {
+                let _ = writeln!(io.stderr, "[: missing `]'");
+                2
+            }
+        }
+    }
+}
+
+fn report(name: &str, result: Result<bool, String>, io: &mut Io) -> i32 {
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(message) => {
+            let _ = writeln!(io.stderr, "{}: {}", name, message);
+            2
+        }
+    }
+}
+
+/// Evaluates a `test` expression. Up to four arguments follow the POSIX rules,
+/// which decide by argument count alone, so `test -n` or `test ! = x` behave
+/// as in other shells; longer expressions are parsed with the usual precedence.
+fn evaluate(args: &[String]) -> Result<bool, String> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args[..] {
+        [] => Ok(false),
+        [arg] => Ok(!arg.is_empty()),
+        ["!", arg] => Ok(arg.is_empty()),
+        [op, arg] if is_unary(op) => unary_test(op, arg),
+        [op, _] => Err(format!("{}: unary operator expected", op)),
+        [lhs, op, rhs] if is_binary(op) => binary_test(lhs, op, rhs),
+        [lhs, "-a", rhs] => Ok(!lhs.is_empty() && !rhs.is_empty()),
+        [lhs, "-o", rhs] => Ok(!lhs.is_empty() || !rhs.is_empty()),
+        ["!", ..] if args.len() <= 4 => evaluate_strs(&args[1..]).map(|result| !result),
+        ["(", .., ")"] if args.len() <= 4 => evaluate_strs(&args[1..args.len() - 1]),
+        [_, op, _] => Err(format!("{}: binary operator expected", op)),
+        _ => {
+            let mut parser = Parser { args: &args, pos: 0 };
+            let result = parser.or()?;
+            match parser.args.get(parser.pos) {
+                None => Ok(result),
+                Some(_) => Err("too many arguments".to_string()),
+            }
+        }
+    }
+}
+
+fn evaluate_strs(args: &[&str]) -> Result<bool, String> {
+    evaluate(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+}
+
+/// Recursive-descent parser for `test` expressions longer than four arguments.
+struct Parser<'a> {
+    args: &'a [&'a str],
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self, offset: usize) -> Option<&'a str> {
+        self.args.get(self.pos + offset).copied()
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let arg = self.args.get(self.pos).copied().ok_or("argument expected")?;
+        self.pos += 1;
+        Ok(arg)
+    }
+
+    fn or(&mut self) -> Result<bool, String> {
+        let mut result = self.and()?;
+        while self.peek(0) == Some("-o") {
+            self.pos += 1;
+            result |= self.and()?;
+        }
+        Ok(result)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let mut result = self.not()?;
+        while self.peek(0) == Some("-a") {
+            self.pos += 1;
+            result &= self.not()?;
+        }
+        Ok(result)
+    }
+
+    fn not(&mut self) -> Result<bool, String> {
+        if self.peek(0) == Some("!") && self.peek(1).is_some() {
+            self.pos += 1;
+            return self.not().map(|result| !result);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<bool, String> {
+        if self.peek(0) == Some("(") {
+            self.pos += 1;
+            let result = self.or()?;
+            return match self.next() {
+                Ok(")") => Ok(result),
+                _ => Err("`)' expected".to_string()),
+            };
+        }
+        if let Some(op) = self.peek(1).filter(|op| is_binary(op)) {
+            let lhs = self.next()?;
+            self.pos += 1;
+            let rhs = self.next()?;
+            return binary_test(lhs, op, rhs);
+        }
+        let arg = self.next()?;
+        if is_unary(arg) {
+            if let Some(operand) = self.peek(0) {
+                self.pos += 1;
+                return unary_test(arg, operand);
+            }
+        }
+        Ok(!arg.is_empty())
+    }
+}
+
+fn is_unary(op: &str) -> bool {
+    matches!(op, "-e" | "-f" | "-d" | "-x" | "-r" | "-w" | "-s" | "-L" | "-h" | "-b" | "-c" | "-p" | "-S" | "-t" | "-z" | "-n")
+}
+
+fn is_binary(op: &str) -> bool {
+    matches!(
+        op,
+        "=" | "==" | "!=" | "<" | ">" | "-eq" | "-ne" | "-lt" | "-le" | "-gt" | "-ge" | "-nt" | "-ot" | "-ef"
+    )
+}
+
+fn unary_test(op: &str, operand: &str) -> Result<bool, String> {
+    let metadata = || fs::metadata(operand).ok();
+    Ok(match op {
+        "-z" => operand.is_empty(),
+        "-n" => !operand.is_empty(),
+        "-e" => metadata().is_some(),
+        "-f" => metadata().is_some_and(|m| m.is_file()),
+        "-d" => metadata().is_some_and(|m| m.is_dir()),
+        "-s" => metadata().is_some_and(|m| m.len() > 0),
+        "-b" => metadata().is_some_and(|m| m.file_type().is_block_device()),
+        "-c" => metadata().is_some_and(|m| m.file_type().is_char_device()),
+        "-p" => metadata().is_some_and(|m| m.file_type().is_fifo()),
+        "-S" => metadata().is_some_and(|m| m.file_type().is_socket()),
+        "-L" | "-h" => fs::symlink_metadata(operand).is_ok_and(|m| m.file_type().is_symlink()),
+        "-r" => access(operand, AccessFlags::R_OK).is_ok(),
+        "-w" => access(operand, AccessFlags::W_OK).is_ok(),
+        "-x" => access(operand, AccessFlags::X_OK).is_ok(),
+        "-t" => isatty(parse_integer(operand)? as i32).unwrap_or(false),
+        _ => return Err(format!("{}: unary operator expected", op)),
+    })
+}
+
+fn binary_test(lhs: &str, op: &str, rhs: &str) -> Result<bool, String> {
+    let modified = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+    Ok(match op {
+        "=" | "==" => lhs == rhs,
+        "!=" => lhs != rhs,
+        "<" => lhs < rhs,
+        ">" => lhs > rhs,
+        "-eq" => parse_integer(lhs)? == parse_integer(rhs)?,
+        "-ne" => parse_integer(lhs)? != parse_integer(rhs)?,
+        "-lt" => parse_integer(lhs)? < parse_integer(rhs)?,
+        "-le" => parse_integer(lhs)? <= parse_integer(rhs)?,
+        "-gt" => parse_integer(lhs)? > parse_integer(rhs)?,
+        "-ge" => parse_integer(lhs)? >= parse_integer(rhs)?,
+        // A file that exists is newer than one that does not.
+        "-nt" => match (modified(lhs), modified(rhs)) {
+            (Some(a), Some(b)) => a > b,
+            (a, _) => a.is_some(),
+        },
+        "-ot" => match (modified(lhs), modified(rhs)) {
+            (Some(a), Some(b)) => a < b,
+            (_, b) => b.is_some(),
+        },
+        "-ef" => match (fs::metadata(lhs), fs::metadata(rhs)) {
+            (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+            _ => false,
+        },
+        _ => return Err(format!("{}: binary operator expected", op)),
+    })
+}
+
+fn parse_integer(arg: &str) -> Result<i64, String> {
+    arg.trim().parse().map_err(|_| format!("{}: integer expression expected", arg))
+}
+
+/// Evaluates the words of a `[[ ... ]]` command and returns its status.
+///
+/// Unlike `test`, operands are expanded here without word splitting, `&&` and
+/// `||` combine expressions, the right side of `==` and `!=` is a pattern and
+/// `=~` matches an extended regular expression, storing the match and its
+/// groups in `BASH_REMATCH`. Quoted parts of a pattern or regex match literally.
+pub fn eval_conditional(words: &[String]) -> i32 {
+    let mut parser = Conditional { words, pos: 0, skip: false };
+    let result = parser.or().and_then(|result| match parser.words.get(parser.pos) {
+        None => Ok(result),
+        Some(word) => Err(format!("syntax error in conditional expression: unexpected token `{}'", word)),
+    });
+    match result {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(message) => {
+            eprintln!("{}", message);
+            2
+        }
+    }
+}
+
+struct Conditional<'a> {
+    words: &'a [String],
+    pos: usize,
+    /// Set once the result no longer depends on what is being parsed, which is
+    /// then only checked for syntax: nothing is expanded or tested.
+    skip: bool,
+}
+
+impl<'a> Conditional<'a> {
+    fn peek(&self, offset: usize) -> Option<&'a str> {
+        self.words.get(self.pos + offset).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Result<&'a str, String> {
+        let word = self.words.get(self.pos).ok_or("unexpected argument to conditional expression")?;
+        self.pos += 1;
+        Ok(word)
+    }
+
+    fn or(&mut self) -> Result<bool, String> {
+        let mut result = self.and()?;
+        while self.peek(0) == Some("||") {
+            self.pos += 1;
+            let skip = self.skip;
+            self.skip |= result;
+            let rhs = self.and()?;
+            self.skip = skip;
+            result = result || rhs;
+        }
+        Ok(result)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let mut result = self.not()?;
+        while self.peek(0) == Some("&&") {
+            self.pos += 1;
+            let skip = self.skip;
+            self.skip |= !result;
+            let rhs = self.not()?;
+            self.skip = skip;
+            result = result && rhs;
+        }
+        Ok(result)
+    }
+
+    fn not(&mut self) -> Result<bool, String> {
+        if self.peek(0) == Some("!") && self.peek(1).is_some() {
+            self.pos += 1;
+            return self.not().map(|result| !result);
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<bool, String> {
+        if self.peek(0) == Some("(") {
+            self.pos += 1;
+            let result = self.or()?;
+            return match self.next() {
+                Ok(")") => Ok(result),
+                _ => Err("syntax error in conditional expression: expected `)'".to_string()),
+            };
+        }
+        if let Some(op) = self.peek(1).filter(|op| is_binary(op) || *op == "=~") {
+            let lhs = self.next()?;
+            self.pos += 1;
+            let rhs = self.next()?;
+            if self.skip {
+                return Ok(false);
+            }
+            let lhs = expand(lhs);
+            return match op {
+                "=" | "==" => Ok(pattern_matches(rhs, &lhs)),
+                "!=" => Ok(!pattern_matches(rhs, &lhs)),
+                "=~" => regex_match(&lhs, rhs),
+                _ => binary_test(&lhs, op, &expand(rhs)),
+            };
+        }
+        let word = self.next()?;
+        if is_unary(word) {
+            if let Some(operand) = self.peek(0) {
+                self.pos += 1;
+                if self.skip {
+                    return Ok(false);
+                }
+                return unary_test(word, &expand(operand));
+            }
+        }
+        Ok(!self.skip && !expand(word).is_empty())
+    }
+}
+
+fn expand(raw: &str) -> String {
+    unquoted(&expand_word(raw))
+}
+
+/// Matches `text` against the unexpanded pattern `raw`, ignoring case under `shopt -s nocasematch`.
+fn pattern_matches(raw: &str, text: &str) -> bool {
+    let pattern = pattern::escape(&expand_word(raw));
+    if shopt::is_enabled("nocasematch") {
+        pattern::matches(&pattern.to_lowercase(), &text.to_lowercase())
+    } else {
+        pattern::matches(&pattern, text)
+    }
+}
+
+fn regex_match(text: &str, raw: &str) -> Result<bool, String> {
+    let mut source = String::new();
+    for (c, quoted) in expand_word(raw) {
+        if quoted {
+            source.push_str(&regex::escape(&c.to_string()));
+        } else {
+            source.push(c);
+        }
+    }
+    let regex = regex::RegexBuilder::new(&source)
+        .case_insensitive(shopt::is_enabled("nocasematch"))
+        .build()
+        .map_err(|_| format!("{}: invalid regular expression", source))?;
+    let groups = match regex.captures(text) {
+        Some(captures) => captures
+            .iter()
+            .map(|group| group.map_or_else(String::new, |m| m.as_str().to_string()))
+            .collect(),
+        None => Vec::new(),
+    };
+    let matched = !groups.is_empty();
+    vars::set_array("BASH_REMATCH", groups);
+    Ok(matched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test(expression: &str) -> Result<bool, String> {
+        evaluate_strs(&expression.split(' ').collect::<Vec<_>>())
+    }
+
+    fn conditional(expression: &str) -> i32 {
+        let words: Vec<String> = expression.split(' ').map(str::to_string).collect();
+        eval_conditional(&words)
+    }
+
+    #[test]
+    fn decides_short_tests_by_argument_count() {
+        assert_eq!(test("-n"), Ok(true));
+        assert_eq!(test("! = x"), Ok(false));
+        assert_eq!(test("abc = abc"), Ok(true));
+        assert_eq!(test("2 -lt 10"), Ok(true));
+        assert_eq!(test("( x )"), Ok(true));
+        assert_eq!(test("-q x"), Err("-q: unary operator expected".to_string()));
+    }
+
+    #[test]
+    fn parses_long_tests_with_precedence() {
+        assert_eq!(test("a = b -o 1 -eq 1 -a ! -z x"), Ok(true));
+        assert_eq!(test("( a = b -o 1 -eq 1 ) -a -z x"), Ok(false));
+        assert_eq!(test("a = a b"), Err("too many arguments".to_string()));
+    }
+
+    #[test]
+    fn matches_patterns_and_regexes() {
+        assert_eq!(conditional("abc == a*"), 0);
+        assert_eq!(conditional("abc == 'a*'"), 1);
+        assert_eq!(conditional("abc != a?"), 0);
+        assert_eq!(conditional("key=42 =~ ^([a-z]+)=([0-9]+)$"), 0);
+        assert_eq!(vars::get("BASH_REMATCH").as_deref(), Some("key=42"));
+        assert_eq!(conditional("a.c =~ 'a.c'"), 0);
+        assert_eq!(conditional("abc =~ 'a.c'"), 1);
+    }
+
+    #[test]
+    fn groups_and_negates() {
+        assert_eq!(conditional("! ( 1 -lt 2 && 3 -gt 4 )"), 0);
+        assert_eq!(conditional("-z '' && ! -n ''"), 0);
+        assert_eq!(conditional("( a"), 2);
+    }
+
+    #[test]
+    fn short_circuits_and_and_or() {
+        assert_eq!(conditional("a == a || $((test_skipped_or=1)) -eq 1"), 0);
+        assert_eq!(conditional("a == b && $((test_skipped_and=1)) -eq 1"), 1);
+        assert_eq!(vars::get("test_skipped_or"), None);
+        assert_eq!(vars::get("test_skipped_and"), None);
+        assert_eq!(conditional("a == b || $((test_evaluated=1)) -eq 1"), 0);
+        assert_eq!(vars::get("test_evaluated").as_deref(), Some("1"));
+        // A skipped operand is still checked for syntax.
+        assert_eq!(conditional("a == a || -q x"), 2);
+    }
+}
@@ -0,0 +1,103 @@
+use std::io::Write;
+
+use crate::commands::{Builtin, Io};
+use crate::traps::{self, Condition, CONDITIONS};
+
+const USAGE: &str = "trap [-lp] [[arg] signal_spec ...]";
+
+pub struct Trap;
+
+impl Builtin for Trap {
+    fn name(&self) -> &'static str { "trap" }
+
+    fn help(&self) -> &'static str {
+        "trap [-lp] [[arg] signal_spec ...]\n    Run ARG when the shell receives a signal or meets a condition. Each\n    SIGNAL_SPEC is HUP, INT, TERM, USR1, USR2 or CHLD, with or without a SIG\n    prefix, or its number, or one of:\n      EXIT    when the shell exits (also 0)\n      DEBUG   before each command\n      ERR     when a command fails where `set -e' would exit\n      RETURN  when a function or sourced script returns; accepted, but it\n              never runs while the shell has no functions or `source'\n    Signal traps run once the command in progress finishes. If ARG is the empty\n    string the signal is ignored; if it is `-' or omitted, the trap is reset.\n    -l  list the signal names and their numbers, then the other conditions\n    -p  display the trap commands for each SIGNAL_SPEC, or for all traps"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let mut args = args;
+        let mut print = false;
+        while let Some(arg) = args.first().filter(|arg| arg.starts_with('-') && arg.len() > 1) {
+            args = &args[1..];
+            match arg.as_str() {
+                "--" => break,
+                "-l" => {
+                    list_signals(io);
+                    return 0;
+                }
+                "-p" => print = true,
+                _ => {
+                    let _ = writeln!(io.stderr, "trap: {}: invalid option", arg);
+                    let _ = writeln!(io.stderr, "trap: usage: {}", USAGE);
+                    return 2;
+                }
+            }
+        }
+        if print || args.is_empty() {
+            return print_traps(args, io);
+        }
+        // A lone signal spec, or `-`, resets; anything else is the command.
+        let (command, specs) = match args {
+            [spec] if traps::parse_condition(spec).is_some() => (None, args),
+            [_] => {
+                let _ = writeln!(io.stderr, "trap: usage: {}", USAGE);
+                return 2;
+            }
+            [action, specs @ ..] if action == "-" => (None, specs),
+            [action, specs @ ..] => (Some(action), specs),
+            [] => unreachable!(),
+        };
+        let mut status = 0;
+        for spec in specs {
+            match traps::parse_condition(spec) {
+                Some(index) => traps::set(index, command.cloned()),
+                None => {
+                    let _ = writeln!(io.stderr, "trap: {}: invalid signal specification", spec);
+                    status = 1;
+                }
+            }
+        }
+        status
+    }
+}
+
+/// Prints the traps for `specs`, or every trap, as commands that set them again.
+fn print_traps(specs: &[String], io: &mut Io) -> i32 {
+    let mut status = 0;
+    let mut wanted = Vec::new();
+    for spec in specs {
+        match traps::parse_condition(spec) {
+            Some(index) => wanted.push(index),
+            None => {
+                let _ = writeln!(io.stderr, "trap: {}: invalid signal specification", spec);
+                status = 1;
+            }
+        }
+    }
+    for (index, command) in traps::traps() {
+        if specs.is_empty() || wanted.contains(&index) {
+            let _ = writeln!(io.stdout, "trap -- '{}' {}", command.replace('\'', "'\\''"), traps::display_name(index));
+        }
+    }
+    status
+}
+
+fn list_signals(io: &mut Io) {
+    let signals: Vec<String> = CONDITIONS
+        .iter()
+        .enumerate()
+        .filter_map(|(index, (_, condition))| match condition {
+            Condition::Signal(signal) => Some(format!("{:2}) {}", *signal as i32, traps::display_name(index))),
+            _ => None,
+        })
+        .collect();
+    for row in signals.chunks(5) {
+        let _ = writeln!(io.stdout, "{}", row.join("\t"));
+    }
+    let others: Vec<&str> = CONDITIONS
+        .iter()
+        .filter(|(_, condition)| !matches!(condition, Condition::Signal(_)))
+        .map(|(name, _)| *name)
+        .collect();
+    let _ = writeln!(io.stdout, "{}", others.join("\t"));
+}
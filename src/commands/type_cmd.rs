@@ -1,32 +1,270 @@
 use std::env;
+use std::io::Write;
 use std::path::Path;
 
-pub fn handle_type_command(input: &str) -> bool {
-    // Check if the input starts with "type " (with a space)
-    if let Some(rest) = input.strip_prefix("type ") {
-        let cmd = rest.trim();
-        // List of builtins
-        let builtins = ["echo", "exit", "type", "pwd", "cd"];
-        if builtins.contains(&cmd) {
-            println!("{} is a shell builtin", cmd);
-        } else if let Some(path) = find_executable(cmd) {
-            println!("{} is {}", cmd, path);
-        } else {
-            println!("{}: not found", cmd);
+use thiserror::Error;
+
+use crate::commands::{hash, lookup, Builtin, Io, KEYWORDS};
+
+/// What a command name resolves to, in the order the shell would consider them.
+#[derive(Debug)]
+pub enum CommandKind {
+    Keyword,
+    Builtin,
+    File(String),
+}
+
+impl CommandKind {
+    /// The single-word description printed by `type -t`.
+    pub fn label(&self) -> &'static str {
+        match self {
+            CommandKind::Keyword => "keyword",
+            CommandKind::Builtin => "builtin",
+            CommandKind::File(_) => "file",
+        }
+    }
+
+    /// The sentence printed by plain `type` and `command -V`.
+    pub fn describe(&self, name: &str) -> String {
+        match self {
+            CommandKind::Keyword => format!("{} is a shell keyword", name),
+            CommandKind::Builtin => format!("{} is a shell builtin", name),
+            CommandKind::File(path) => format!("{} is {}", name, path),
         }
-        return true;
     }
-    false
 }
 
-pub fn find_executable(command: &str) -> Option<String> {
-    if let Ok(paths) = env::var("PATH") {
-        for dir in paths.split(':') {
-            let full_path = format!("{}/{}", dir, command);
-            if Path::new(&full_path).exists() {
-                return Some(full_path);
+/// Lists what `name` resolves to. With `all` set every PATH match is included,
+/// otherwise the search stops at the first hit.
+pub fn classify(name: &str, all: bool) -> Vec<CommandKind> {
+    let mut kinds = Vec::new();
+    if KEYWORDS.contains(&name) {
+        kinds.push(CommandKind::Keyword);
+    }
+    if lookup(name).is_some() {
+        kinds.push(CommandKind::Builtin);
+    }
+    if all {
+        kinds.extend(find_all_executables(name).into_iter().map(CommandKind::File));
+    } else if kinds.is_empty() {
+        kinds.extend(find_executable(name).map(CommandKind::File));
+    }
+    kinds
+}
+
+pub struct Type;
+
+impl Builtin for Type {
+    fn name(&self) -> &'static str { "type" }
+
+    fn help(&self) -> &'static str {
+        "type [-afptP] name [name ...]\n    Describe how each NAME would be interpreted as a command.\n    -a  list every match, including all PATH entries\n    -t  print only the kind: keyword, builtin or file\n    -p  print the path that would be executed, if it is a file\n    -P  search PATH even if NAME is also a builtin or keyword"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut all, mut kind_only, mut path_only, mut force_path) = (false, false, false, false);
+        let mut names = Vec::new();
+        let mut options_done = false;
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some("-") if !options_done => options_done = true,
+                Some(flags) if !options_done && !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'a' => all = true,
+                            't' => kind_only = true,
+                            'p' => path_only = true,
+                            'P' => force_path = true,
+                            // There are no functions to skip, so -f is accepted and ignored.
+                            'f' => {}
+                            _ => {
+                                let _ = writeln!(io.stderr, "type: -{}: invalid option", flag);
+                                let _ = writeln!(io.stderr, "type: usage: type [-afptP] name [name ...]");
+                                return 2;
+                            }
+                        }
+                    }
+                }
+                _ => {
+                    options_done = true;
+                    names.push(arg.as_str());
+                }
+            }
+        }
+
+        let mut status = 0;
+        for name in names {
+            let mut kinds = classify(name, all || force_path);
+            if force_path {
+                kinds.retain(|kind| matches!(kind, CommandKind::File(_)));
+                if !all {
+                    kinds.truncate(1);
+                }
             }
+            if kinds.is_empty() {
+                if !kind_only && !path_only && !force_path {
+                    let _ = writeln!(io.stdout, "{}: not found", name);
+                }
+                status = 1;
+                continue;
+            }
+            for kind in &kinds {
+                if kind_only {
+                    let _ = writeln!(io.stdout, "{}", kind.label());
+                } else if path_only || force_path {
+                    if let CommandKind::File(path) = kind {
+                        let _ = writeln!(io.stdout, "{}", path);
+                    }
+                } else {
+                    let _ = writeln!(io.stdout, "{}", kind.describe(name));
+                }
+            }
+        }
+        status
+    }
+}
+
+/// Returns every executable named `command` along PATH, in search order.
+pub fn find_all_executables(command: &str) -> Vec<String> {
+    if command.contains('/') {
+        return find_executable(command).into_iter().collect();
+    }
+    executables_in(command, &env::var("PATH").unwrap_or_default())
+}
+
+/// Returns every executable named `command` in the directories of `path_var`,
+/// in search order, without consulting the hash table.
+pub fn executables_in(command: &str, path_var: &str) -> Vec<String> {
+    let mut found = Vec::new();
+    for dir in path_var.split(':') {
+        let dir = if dir.is_empty() { "." } else { dir };
+        let full_path = Path::new(dir).join(command);
+        if hash::is_executable(&full_path) {
+            found.push(full_path.to_string_lossy().into_owned());
+        }
+    }
+    found
+}
+
+#[derive(Debug, Error)]
+pub enum LookupError {
+    #[error("{0}: command not found")]
+    NotFound(String),
+    #[error("{0}: No such file or directory")]
+    NoSuchFile(String),
+    #[error("{0}: Is a directory")]
+    IsADirectory(String),
+    #[error("{0}: Permission denied")]
+    PermissionDenied(String),
+}
+
+impl LookupError {
+    /// The exit status a shell reports for this failure.
+    pub fn status(&self) -> i32 {
+        match self {
+            LookupError::NotFound(_) | LookupError::NoSuchFile(_) => 127,
+            LookupError::IsADirectory(_) | LookupError::PermissionDenied(_) => 126,
         }
     }
-    None
-}
\ No newline at end of file
+}
+
+pub fn find_executable(command: &str) -> Option<String> {
+    resolve_command(command).ok()
+}
+
+/// Whether `command` would resolve to something executable, checked without
+/// adding to the hash table or counting a hit.
+pub fn can_execute(command: &str) -> bool {
+    if command.contains('/') {
+        let path = Path::new(command);
+        return !path.is_dir() && hash::is_executable(path);
+    }
+    !command.is_empty() && hash::peek(command).is_some()
+}
+
+/// Resolves `command` to the path that would be executed.
+///
+/// Names containing a slash are used as-is; anything else goes through the hash
+/// table and then PATH, where empty components stand for the current directory.
+pub fn resolve_command(command: &str) -> Result<String, LookupError> {
+    if command.contains('/') {
+        let path = Path::new(command);
+        return if !path.exists() {
+            Err(LookupError::NoSuchFile(command.to_string()))
+        } else if path.is_dir() {
+            Err(LookupError::IsADirectory(command.to_string()))
+        } else if !hash::is_executable(path) {
+            Err(LookupError::PermissionDenied(command.to_string()))
+        } else {
+            Ok(command.to_string())
+        };
+    }
+    if command.is_empty() {
+        return Err(LookupError::NotFound(command.to_string()));
+    }
+    if let Some(path) = hash::lookup(command) {
+        return Ok(path);
+    }
+
+    // Slow path: walk PATH ourselves so that a non-executable match can be
+    // reported as "Permission denied" rather than "not found".
+    let mut denied = false;
+    for dir in env::var("PATH").unwrap_or_default().split(':') {
+        let dir = if dir.is_empty() { "." } else { dir };
+        let full_path = Path::new(dir).join(command);
+        if full_path.is_dir() || !full_path.exists() {
+            continue;
+        }
+        if hash::is_executable(&full_path) {
+            return Ok(full_path.to_string_lossy().into_owned());
+        }
+        denied = true;
+    }
+    if denied {
+        Err(LookupError::PermissionDenied(command.to_string()))
+    } else {
+        Err(LookupError::NotFound(command.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn status(command: &str) -> i32 {
+        resolve_command(command).map_or_else(|e| e.status(), |_| 0)
+    }
+
+    #[test]
+    fn reports_126_for_files_that_cannot_run_and_127_for_missing_ones() {
+        let dir = env::temp_dir().join(format!("shell-test-{}-resolve", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (plain, runnable) = (dir.join("plain"), dir.join("runnable"));
+        fs::write(&plain, "").unwrap();
+        fs::write(&runnable, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&runnable, fs::Permissions::from_mode(0o755)).unwrap();
+        let path = |p: &Path| p.to_string_lossy().into_owned();
+
+        assert_eq!(resolve_command(&path(&runnable)).ok(), Some(path(&runnable)));
+        assert_eq!(status(&path(&plain)), 126);
+        assert_eq!(status(&path(&dir)), 126);
+        assert_eq!(status(&path(&dir.join("missing"))), 127);
+        assert_eq!(status("shell-test-no-such-command"), 127);
+        assert_eq!(status(""), 127);
+        assert_eq!(resolve_command(&path(&dir)).unwrap_err().to_string(), format!("{}: Is a directory", path(&dir)));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn reports_only_parsed_reserved_words_as_keywords() {
+        for word in ["!", "[[", "]]"] {
+            assert!(matches!(classify(word, false).as_slice(), [CommandKind::Keyword]));
+        }
+        for word in ["if", "{", "while"] {
+            assert!(!classify(word, false).iter().any(|kind| matches!(kind, CommandKind::Keyword)));
+        }
+    }
+}
@@ -0,0 +1,54 @@
+use std::io::Write;
+
+use crate::commands::{Builtin, Io};
+use crate::util::resolve_subscript;
+use crate::vars;
+
+pub struct Unset;
+
+impl Builtin for Unset {
+    fn name(&self) -> &'static str { "unset" }
+
+    fn help(&self) -> &'static str {
+        "unset [-v] [name ...]\n    Remove each variable NAME. `name[subscript]' removes a single array\n    element, and `name[@]' the whole array."
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let mut names = args;
+        while let Some((arg, rest)) = names.split_first() {
+            match arg.as_str() {
+                "-v" => names = rest,
+                "--" => {
+                    names = rest;
+                    break;
+                }
+                _ if arg.starts_with('-') && arg.len() > 1 => {
+                    let _ = writeln!(io.stderr, "unset: {}: invalid option", arg);
+                    let _ = writeln!(io.stderr, "unset: usage: unset [-v] [name ...]");
+                    return 2;
+                }
+                _ => break,
+            }
+        }
+        let mut status = 0;
+        for arg in names {
+            let (name, subscript) = match arg.strip_suffix(']').and_then(|arg| arg.split_once('[')) {
+                Some((name, subscript)) => (name, Some(subscript)),
+                None => (arg.as_str(), None),
+            };
+            if !vars::is_valid_name(name) {
+                let _ = writeln!(io.stderr, "unset: `{}': not a valid identifier", arg);
+                status = 1;
+                continue;
+            }
+            match subscript {
+                None | Some("@" | "*") => vars::unset(name),
+                Some(subscript) => match resolve_subscript(name, subscript) {
+                    Some(key) => vars::unset_element(name, &key),
+                    None => status = 1,
+                },
+            }
+        }
+        status
+    }
+}
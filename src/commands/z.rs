@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::commands::cd::{change_directory, error_message, logical_cwd};
+use crate::commands::{Builtin, Io};
+
+/// Once the ranks add up to more than this, every entry is aged.
+const MAX_TOTAL_RANK: f64 = 9000.0;
+
+/// One line of the database: `path|rank|last-visit`, the format used by `z.sh`
+/// so an existing `~/.z` keeps working.
+struct Entry {
+    path: String,
+    rank: f64,
+    time: u64,
+}
+
+impl Entry {
+    /// Weights the visit count by how recently the directory was used.
+    fn frecency(&self, now: u64) -> f64 {
+        let age = now.saturating_sub(self.time);
+        let factor = match age {
+            0..=3_600 => 4.0,
+            3_601..=86_400 => 2.0,
+            86_401..=604_800 => 0.5,
+            _ => 0.25,
+        };
+        self.rank * factor
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// `$_Z_DATA`, falling back to `~/.z`.
+fn database_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("_Z_DATA") {
+        return Some(PathBuf::from(path));
+    }
+    env::var("HOME").ok().map(|home| PathBuf::from(home).join(".z"))
+}
+
+fn parse_line(line: &str) -> Option<Entry> {
+    let mut fields = line.rsplitn(3, '|');
+    let time = fields.next()?.parse().ok()?;
+    let rank = fields.next()?.parse().ok()?;
+    let path = fields.next()?.to_string();
+    Some(Entry { path, rank, time })
+}
+
+/// Reads the database, along with how many lines it took. Visits are appended
+/// as lines of their own, so lines for the same directory are merged.
+fn load() -> (Vec<Entry>, usize) {
+    let Some(path) = database_path() else { return (Vec::new(), 0) };
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut lines = 0;
+    for entry in contents.lines().filter_map(parse_line) {
+        lines += 1;
+        match positions.get(&entry.path) {
+            Some(&i) => {
+                let existing = &mut entries[i];
+                existing.rank += entry.rank;
+                existing.time = existing.time.max(entry.time);
+            }
+            None => {
+                positions.insert(entry.path.clone(), entries.len());
+                entries.push(entry);
+            }
+        }
+    }
+    (entries, lines)
+}
+
+fn save(entries: &[Entry]) {
+    let Some(path) = database_path() else { return };
+    let contents: String = entries
+        .iter()
+        .map(|e| format!("{}|{}|{}\n", e.path, e.rank, e.time))
+        .collect();
+    let _ = fs::write(path, contents);
+}
+
+/// Records a visit to `dir`; called when a command typed at the prompt changes directory.
+///
+/// The visit is appended to the database. The whole file is only rewritten to
+/// age the entries, or to merge lines once there are twice as many as directories.
+pub fn record_visit(dir: &Path) {
+    let path = dir.display().to_string();
+    if env::var("HOME").is_ok_and(|home| home == path) {
+        return;
+    }
+    let (mut entries, lines) = load();
+    let time = now();
+    match entries.iter_mut().find(|e| e.path == path) {
+        Some(entry) => {
+            entry.rank += 1.0;
+            entry.time = time;
+        }
+        None => entries.push(Entry { path: path.clone(), rank: 1.0, time }),
+    }
+    let aging = entries.iter().map(|e| e.rank).sum::<f64>() > MAX_TOTAL_RANK;
+    if aging {
+        for entry in &mut entries {
+            entry.rank *= 0.99;
+        }
+        entries.retain(|e| e.rank >= 1.0);
+    }
+    if aging || lines + 1 > 2 * entries.len() {
+        save(&entries);
+        return;
+    }
+    let Some(database) = database_path() else { return };
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(database) {
+        let _ = writeln!(file, "{}|1|{}", path, time);
+    }
+}
+
+/// Returns true if every term occurs in `path`, each after the previous one.
+fn matches(path: &str, terms: &[String], ignore_case: bool) -> bool {
+    let path = if ignore_case { path.to_lowercase() } else { path.to_string() };
+    let mut rest = path.as_str();
+    for term in terms {
+        let term = if ignore_case { term.to_lowercase() } else { term.clone() };
+        match rest.find(&term) {
+            Some(i) => rest = &rest[i + term.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+pub struct Z;
+
+impl Builtin for Z {
+    fn name(&self) -> &'static str { "z" }
+
+    fn help(&self) -> &'static str {
+        "z [-lrtx] [term ...]\n    Jump to the most frecent visited directory matching every TERM in order.\n    -l  list matches with their scores instead of jumping\n    -r  rank by visit count only\n    -t  rank by most recent visit only\n    -x  remove the current directory from the database"
+    }
+
+    fn run(&self, args: &[String], io: &mut Io) -> i32 {
+        let (mut list, mut by_rank, mut by_time) = (false, false, false);
+        let mut terms = Vec::new();
+        for arg in args {
+            match arg.strip_prefix('-') {
+                Some(flags) if terms.is_empty() && !flags.is_empty() => {
+                    for flag in flags.chars() {
+                        match flag {
+                            'l' => list = true,
+                            'r' => by_rank = true,
+                            't' => by_time = true,
+                            'x' => {
+                                let cwd = logical_cwd().display().to_string();
+                                let (mut entries, _) = load();
+                                entries.retain(|e| e.path != cwd);
+                                save(&entries);
+                                return 0;
+                            }
+                            _ => {
+                                let _ = writeln!(io.stderr, "z: -{}: invalid option", flag);
+                                return 2;
+                            }
+                        }
+                    }
+                }
+                _ => terms.push(arg.clone()),
+            }
+        }
+
+        let now = now();
+        let score = |e: &Entry| {
+            if by_rank {
+                e.rank
+            } else if by_time {
+                e.time as f64
+            } else {
+                e.frecency(now)
+            }
+        };
+        let entries: Vec<Entry> = load().0.into_iter().filter(|e| Path::new(&e.path).is_dir()).collect();
+        let mut found: Vec<&Entry> = entries.iter().filter(|e| matches(&e.path, &terms, false)).collect();
+        if found.is_empty() {
+            found = entries.iter().filter(|e| matches(&e.path, &terms, true)).collect();
+        }
+        found.sort_by(|a, b| score(a).total_cmp(&score(b)));
+
+        if list || terms.is_empty() {
+            for entry in &found {
+                let _ = writeln!(io.stdout, "{:<10} {}", format!("{:.1}", score(entry)), entry.path);
+            }
+            return 0;
+        }
+        let Some(best) = found.last() else {
+            let _ = writeln!(io.stderr, "z: no match for {}", terms.join(" "));
+            return 1;
+        };
+        match change_directory(Path::new(&best.path), false) {
+            Ok(_) => 0,
+            Err(e) => {
+                let _ = writeln!(io.stderr, "z: {}: {}", best.path, error_message(&e));
+                1
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frecency_weights_rank_by_recency() {
+        let entry = Entry { path: "/src/shell".to_string(), rank: 10.0, time: 1_000_000 };
+        assert_eq!(entry.frecency(1_000_000), 40.0);
+        assert_eq!(entry.frecency(1_000_000 + 3_600), 40.0);
+        assert_eq!(entry.frecency(1_000_000 + 7_200), 20.0);
+        assert_eq!(entry.frecency(1_000_000 + 2 * 86_400), 5.0);
+        assert_eq!(entry.frecency(1_000_000 + 30 * 86_400), 2.5);
+        // A visit recorded "in the future" counts as just now.
+        assert_eq!(entry.frecency(0), 40.0);
+    }
+
+    #[test]
+    fn matches_terms_in_order() {
+        let terms = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+        assert!(matches("/home/me/src/shell", &terms(&["src", "sh"]), false));
+        assert!(!matches("/home/me/src/shell", &terms(&["shell", "src"]), false));
+        assert!(!matches("/home/me/Src", &terms(&["src"]), false));
+        assert!(matches("/home/me/Src", &terms(&["src"]), true));
+        assert!(matches("/anything", &[], false));
+    }
+
+    #[test]
+    fn parses_database_lines() {
+        let entry = parse_line("/src/a|b|12.5|1700000000").unwrap();
+        assert_eq!((entry.path.as_str(), entry.rank, entry.time), ("/src/a|b", 12.5, 1_700_000_000));
+        assert!(parse_line("/src|1").is_none());
+        assert!(parse_line("/src|x|1").is_none());
+    }
+}
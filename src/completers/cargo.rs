@@ -0,0 +1,134 @@
+use std::fs;
+use std::path::Path;
+
+use super::{find_upwards, rust_file_stems};
+
+const SUBCOMMANDS: &[&str] = &[
+    "add", "bench", "build", "check", "clean", "clippy", "doc", "fetch", "fix", "fmt", "init",
+    "install", "metadata", "new", "package", "publish", "remove", "run", "search", "test",
+    "tree", "uninstall", "update",
+];
+
+/// The pieces of a `Cargo.toml` that completion cares about.
+#[derive(Default)]
+struct Manifest {
+    package: Option<String>,
+    members: Vec<String>,
+    bins: Vec<String>,
+    tests: Vec<String>,
+    examples: Vec<String>,
+    benches: Vec<String>,
+}
+
+pub fn complete(previous: &[String], _word: &str) -> Vec<String> {
+    let Some(last) = previous.last() else {
+        return SUBCOMMANDS.iter().map(|s| s.to_string()).collect();
+    };
+    let Some(manifest_path) = find_upwards("Cargo.toml") else { return Vec::new() };
+    let root = manifest_path.parent().unwrap_or(Path::new("."));
+    let manifest = parse_manifest(&manifest_path);
+    match last.as_str() {
+        "-p" | "--package" => workspace_packages(root, &manifest),
+        "--bin" => {
+            let mut bins = manifest.bins;
+            if root.join("src/main.rs").exists() {
+                bins.extend(manifest.package);
+            }
+            bins.extend(rust_file_stems(&root.join("src/bin")));
+            bins
+        }
+        "--test" => [manifest.tests, rust_file_stems(&root.join("tests"))].concat(),
+        "--example" => [manifest.examples, rust_file_stems(&root.join("examples"))].concat(),
+        "--bench" => [manifest.benches, rust_file_stems(&root.join("benches"))].concat(),
+        _ if previous.len() == 1 && last.starts_with('+') => {
+            SUBCOMMANDS.iter().map(|s| s.to_string()).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the root package plus the package name of every workspace member.
+fn workspace_packages(root: &Path, manifest: &Manifest) -> Vec<String> {
+    let mut packages: Vec<String> = manifest.package.iter().cloned().collect();
+    for member in &manifest.members {
+        // Only the common `dir/*` glob form is expanded.
+        let dirs: Vec<_> = match member.strip_suffix("/*") {
+            Some(parent) => fs::read_dir(root.join(parent))
+                .map(|entries| entries.flatten().map(|e| e.path()).collect())
+                .unwrap_or_default(),
+            None => vec![root.join(member)],
+        };
+        for dir in dirs {
+            if let Some(name) = parse_manifest(&dir.join("Cargo.toml")).package {
+                packages.push(name);
+            }
+        }
+    }
+    packages
+}
+
+/// A deliberately small TOML reader: it tracks the current table header and picks
+/// out `name = "..."` keys and the `members = [...]` array.
+fn parse_manifest(path: &Path) -> Manifest {
+    let mut manifest = Manifest::default();
+    let Ok(contents) = fs::read_to_string(path) else { return manifest };
+    let mut table = String::new();
+    let mut in_members = false;
+    for raw in contents.lines() {
+        let line = raw.split('#').next().unwrap_or("").trim();
+        if in_members {
+            manifest.members.extend(quoted_strings(line));
+            in_members = !line.contains(']');
+            continue;
+        }
+        if line.starts_with('[') {
+            table = line.trim_matches(|c| c == '[' || c == ']').trim().to_string();
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let (key, value) = (key.trim(), value.trim());
+        match (table.as_str(), key) {
+            ("workspace", "members") => {
+                manifest.members.extend(quoted_strings(value));
+                in_members = !value.contains(']');
+            }
+            (_, "name") => {
+                let Some(name) = quoted_strings(value).into_iter().next() else { continue };
+                match table.as_str() {
+                    "package" => manifest.package = Some(name),
+                    "bin" => manifest.bins.push(name),
+                    "test" => manifest.tests.push(name),
+                    "example" => manifest.examples.push(name),
+                    "bench" => manifest.benches.push(name),
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+    manifest
+}
+
+/// Extracts every double-quoted string on a line.
+fn quoted_strings(s: &str) -> Vec<String> {
+    s.split('"').skip(1).step_by(2).map(|part| part.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_names_and_members_from_a_manifest() {
+        let path = std::env::temp_dir().join(format!("shell-test-{}-Cargo.toml", std::process::id()));
+        let manifest = "[package]\nname = \"shell\" # the root\n\n[workspace]\nmembers = [\n  \"crates/*\",\n  \"tools\",\n]\n\n\
+                        [[bin]]\nname = \"helper\"\n\n[[test]]\nname = \"cli\"\n";
+        fs::write(&path, manifest).unwrap();
+        let parsed = parse_manifest(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(parsed.package.as_deref(), Some("shell"));
+        assert_eq!(parsed.members, ["crates/*", "tools"]);
+        assert_eq!(parsed.bins, ["helper"]);
+        assert_eq!(parsed.tests, ["cli"]);
+    }
+}
@@ -0,0 +1,75 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::find_upwards;
+
+const SUBCOMMANDS: &[&str] = &[
+    "add", "am", "bisect", "blame", "branch", "checkout", "cherry-pick", "clean", "clone",
+    "commit", "config", "describe", "diff", "fetch", "grep", "init", "log", "merge", "mv",
+    "pull", "push", "rebase", "reflog", "remote", "reset", "restore", "revert", "rm", "show",
+    "stash", "status", "switch", "tag", "worktree",
+];
+
+/// Subcommands whose arguments are usually branch or tag names.
+const REF_SUBCOMMANDS: &[&str] = &[
+    "branch", "checkout", "cherry-pick", "diff", "log", "merge", "rebase", "reset", "revert",
+    "show", "switch", "tag",
+];
+
+pub fn complete(previous: &[String], _word: &str) -> Vec<String> {
+    match previous.iter().find(|arg| !arg.starts_with('-')) {
+        None => SUBCOMMANDS.iter().map(|s| s.to_string()).collect(),
+        Some(sub) if REF_SUBCOMMANDS.contains(&sub.as_str()) => find_git_dir()
+            .map(|dir| read_refs(&dir))
+            .unwrap_or_default(),
+        Some(_) => Vec::new(),
+    }
+}
+
+/// Locates the repository's git directory, following `gitdir:` files used by worktrees.
+fn find_git_dir() -> Option<PathBuf> {
+    let dot_git = find_upwards(".git")?;
+    if dot_git.is_dir() {
+        return Some(dot_git);
+    }
+    let contents = fs::read_to_string(&dot_git).ok()?;
+    let target = contents.trim().strip_prefix("gitdir:")?.trim();
+    Some(dot_git.parent()?.join(target))
+}
+
+/// Returns local branch and tag names from both loose refs and `packed-refs`.
+fn read_refs(git_dir: &Path) -> Vec<String> {
+    let mut refs = Vec::new();
+    for kind in ["heads", "tags"] {
+        let root = git_dir.join("refs").join(kind);
+        collect_loose_refs(&root, &root, &mut refs);
+    }
+    if let Ok(packed) = fs::read_to_string(git_dir.join("packed-refs")) {
+        for line in packed.lines() {
+            if line.starts_with('#') || line.starts_with('^') {
+                continue;
+            }
+            if let Some((_, name)) = line.split_once(' ') {
+                let short = name
+                    .strip_prefix("refs/heads/")
+                    .or_else(|| name.strip_prefix("refs/tags/"));
+                if let Some(short) = short {
+                    refs.push(short.to_string());
+                }
+            }
+        }
+    }
+    refs
+}
+
+fn collect_loose_refs(root: &Path, dir: &Path, refs: &mut Vec<String>) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_loose_refs(root, &path, refs);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            refs.push(relative.to_string_lossy().into_owned());
+        }
+    }
+}
@@ -0,0 +1,63 @@
+pub mod cargo;
+pub mod git;
+pub mod ssh;
+
+use std::path::{Path, PathBuf};
+
+/// Returns argument candidates for `command`, given the words that precede the one
+/// being completed. `None` means no provider knows about the command.
+pub fn complete_argument(command: &str, previous: &[String], word: &str) -> Option<Vec<String>> {
+    let mut candidates = match command {
+        "git" => git::complete(previous, word),
+        "cargo" => cargo::complete(previous, word),
+        "ssh" => ssh::complete(previous, word),
+        _ => return None,
+    };
+    candidates.retain(|c| c.starts_with(word));
+    candidates.sort();
+    candidates.dedup();
+    Some(candidates)
+}
+
+/// Walks up from the current directory looking for `name`, returning its full path.
+fn find_upwards(name: &str) -> Option<PathBuf> {
+    let mut dir = std::env::current_dir().ok()?;
+    loop {
+        let candidate = dir.join(name);
+        if candidate.exists() {
+            return Some(candidate);
+        }
+        if !dir.pop() {
+            return None;
+        }
+    }
+}
+
+/// Returns the stems of the `.rs` files directly inside `dir`.
+fn rust_file_stems(dir: &Path) -> Vec<String> {
+    let mut stems = Vec::new();
+    if let Ok(entries) = std::fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "rs") {
+                if let Some(stem) = path.file_stem().and_then(|s| s.to_str()) {
+                    stems.push(stem.to_string());
+                }
+            }
+        }
+    }
+    stems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filters_candidates_by_the_word() {
+        assert_eq!(complete_argument("git", &[], "st"), Some(vec!["stash".to_string(), "status".to_string()]));
+        assert_eq!(complete_argument("cargo", &[], "b"), Some(vec!["bench".to_string(), "build".to_string()]));
+        assert_eq!(complete_argument("git", &["status".to_string()], ""), Some(Vec::new()));
+        assert_eq!(complete_argument("ls", &[], ""), None);
+    }
+}
@@ -0,0 +1,78 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+pub fn complete(_previous: &[String], word: &str) -> Vec<String> {
+    let Ok(home) = env::var("HOME") else { return Vec::new() };
+    let ssh_dir = PathBuf::from(home).join(".ssh");
+    let mut hosts = config_hosts(&fs::read_to_string(ssh_dir.join("config")).unwrap_or_default());
+    hosts.extend(known_hosts(&fs::read_to_string(ssh_dir.join("known_hosts")).unwrap_or_default()));
+
+    // Keep any `user@` prefix so that `ssh me@ex<TAB>` still completes the host.
+    match word.split_once('@') {
+        Some((user, _)) => hosts.into_iter().map(|h| format!("{}@{}", user, h)).collect(),
+        None => hosts,
+    }
+}
+
+/// Returns the concrete aliases named on `Host` lines, skipping wildcard patterns.
+fn config_hosts(config: &str) -> Vec<String> {
+    let mut hosts = Vec::new();
+    for line in config.lines() {
+        let mut fields = line.split_whitespace();
+        if !fields.next().is_some_and(|key| key.eq_ignore_ascii_case("host")) {
+            continue;
+        }
+        hosts.extend(
+            fields
+                .filter(|h| !h.contains(['*', '?', '!']))
+                .map(|h| h.to_string()),
+        );
+    }
+    hosts
+}
+
+/// Returns host names from `known_hosts`, skipping hashed entries and patterns and
+/// stripping the `[host]:port` form used for non-default ports.
+fn known_hosts(contents: &str) -> Vec<String> {
+    let mut hosts = Vec::new();
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let mut field = fields.next();
+        if field.is_some_and(|f| f.starts_with('@')) {
+            field = fields.next();
+        }
+        let Some(field) = field else { continue };
+        if field.starts_with('#') || field.starts_with('|') {
+            continue;
+        }
+        for host in field.split(',') {
+            let host = match host.strip_prefix('[') {
+                Some(rest) => rest.split(']').next().unwrap_or(rest),
+                None => host,
+            };
+            if !host.contains(['*', '?']) {
+                hosts.push(host.to_string());
+            }
+        }
+    }
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_config_aliases() {
+        let config = "Host work box\n  HostName 10.0.0.1\nhost *.internal !bad\nMatch all\n";
+        assert_eq!(config_hosts(config), ["work", "box"]);
+    }
+
+    #[test]
+    fn reads_known_hosts() {
+        let known = "github.com,140.82.121.4 ssh-ed25519 AAAA\n[git.example]:2222 ssh-rsa AAAA\n\
+                     |1|hashed= ssh-rsa AAAA\n# comment\n@cert-authority *.corp ssh-rsa AAAA\n";
+        assert_eq!(known_hosts(known), ["github.com", "140.82.121.4", "git.example"]);
+    }
+}
@@ -0,0 +1,257 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use nix::libc;
+
+use crate::history;
+
+/// How many matches are shown at once; the list scrolls to keep the selection in view.
+const VISIBLE_ROWS: usize = 10;
+
+/// The line to restart editing with once a search has finished.
+static CHOSEN: Mutex<Option<String>> = Mutex::new(None);
+
+const SELECTED: &str = "\x1b[7m";
+const MATCHED: &str = "\x1b[1;33m";
+const DIM: &str = "\x1b[90m";
+const RESET: &str = "\x1b[0m";
+
+/// Matches `query` as a case-insensitive subsequence of `text`, returning a
+/// score (higher is better) and the character positions that matched.
+///
+/// Each matched character scores, with bonuses when it directly follows the
+/// previous match or starts a word, and a small penalty for every character
+/// skipped in between. Every place the first character occurs is tried as a
+/// starting point and the best-scoring match wins.
+pub fn score(query: &str, text: &str) -> Option<(i64, Vec<usize>)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    let text: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.iter().map(|c| c.to_lowercase().next().unwrap_or(*c)).collect();
+    let Some(&first) = query.first() else {
+        return Some((0, Vec::new()));
+    };
+    let mut best: Option<(i64, Vec<usize>)> = None;
+    for start in (0..lower.len()).filter(|&i| lower[i] == first) {
+        let mut positions = vec![start];
+        let mut next = start + 1;
+        for &c in &query[1..] {
+            match lower[next..].iter().position(|&t| t == c) {
+                Some(offset) => {
+                    positions.push(next + offset);
+                    next += offset + 1;
+                }
+                None => break,
+            }
+        }
+        if positions.len() < query.len() {
+            // Later starts cannot match more of the query.
+            break;
+        }
+        let mut total = 0;
+        for (n, &position) in positions.iter().enumerate() {
+            total += 16;
+            if position == 0 || !text[position - 1].is_alphanumeric() {
+                total += 8;
+            }
+            if n > 0 {
+                let gap = (position - positions[n - 1] - 1) as i64;
+                total += if gap == 0 { 8 } else { -gap.min(8) };
+            }
+        }
+        if best.as_ref().map_or(true, |(b, _)| total > *b) {
+            best = Some((total, positions));
+        }
+    }
+    best
+}
+
+/// History lines matching `query`, best first: by score, with a gentle
+/// preference for recent entries.
+fn ranked(query: &str, lines: &[String]) -> Vec<(usize, Vec<usize>)> {
+    let mut matches: Vec<(i64, usize, Vec<usize>)> = lines
+        .iter()
+        .enumerate()
+        .filter_map(|(age, line)| {
+            let (score, positions) = score(query, line)?;
+            Some((score - (age as i64 + 1).ilog2() as i64, age, positions))
+        })
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+    matches.into_iter().map(|(_, age, positions)| (age, positions)).collect()
+}
+
+/// A key read while searching.
+enum Key {
+    Char(char),
+    Backspace,
+    /// Move the selection down the list, to a worse match.
+    Next,
+    /// Move the selection back up the list.
+    Previous,
+    Accept,
+    Cancel,
+    Other,
+}
+
+/// Reads whatever keys are waiting; typing fast or pasting delivers several at once.
+fn read_keys() -> Vec<Key> {
+    let mut buf = [0u8; 256];
+    let n = match nix::unistd::read(libc::STDIN_FILENO, &mut buf) {
+        Ok(n) if n > 0 => n,
+        _ => return vec![Key::Cancel],
+    };
+    let input = String::from_utf8_lossy(&buf[..n]);
+    let mut keys = Vec::new();
+    let mut rest = input.as_ref();
+    while let Some(c) = rest.chars().next() {
+        let (key, len) = match c {
+            '\x1b' => match rest.get(1..3) {
+                Some("[A" | "OA") => (Key::Previous, 3),
+                Some("[B" | "OB") => (Key::Next, 3),
+                // Escape on its own gives up.
+                None if rest.len() == 1 => (Key::Cancel, 1),
+                _ => (Key::Other, rest.len()),
+            },
+            '\r' | '\n' | '\t' => (Key::Accept, 1),
+            // Ctrl-C and Ctrl-G give up.
+            '\x03' | '\x07' => (Key::Cancel, 1),
+            '\x7f' | '\x08' => (Key::Backspace, 1),
+            // Ctrl-R steps through the matches like repeated reverse search; Ctrl-S goes back.
+            '\x12' | '\x0e' => (Key::Next, 1),
+            '\x13' | '\x10' => (Key::Previous, 1),
+            c if c.is_control() => (Key::Other, 1),
+            c => (Key::Char(c), c.len_utf8()),
+        };
+        keys.push(key);
+        rest = &rest[len..];
+    }
+    keys
+}
+
+fn terminal_width() -> usize {
+    nix::ioctl_read_bad!(window_size, libc::TIOCGWINSZ, libc::winsize);
+    let mut size = libc::winsize { ws_row: 0, ws_col: 0, ws_xpixel: 0, ws_ypixel: 0 };
+    // SAFETY: TIOCGWINSZ only fills in the winsize it is given.
+    match unsafe { window_size(libc::STDOUT_FILENO, &mut size) } {
+        Ok(_) if size.ws_col > 0 => size.ws_col as usize,
+        _ => 80,
+    }
+}
+
+/// Draws the query and the visible part of the match list below the line
+/// being edited, leaving the cursor where it was.
+fn draw(out: &mut impl Write, query: &str, lines: &[String], matches: &[(usize, Vec<usize>)], selected: usize, top: usize) {
+    let width = terminal_width().saturating_sub(3);
+    let mut screen = String::from("\x1b8\r\n\x1b[J");
+    screen.push_str(&format!("{}fuzzy history ({}/{}):{} {}", DIM, matches.len(), lines.len(), RESET, query));
+    for (row, (age, positions)) in matches.iter().enumerate().skip(top).take(VISIBLE_ROWS) {
+        screen.push_str("\r\n");
+        screen.push_str(if row == selected { SELECTED } else { "" });
+        screen.push_str(if row == selected { "> " } else { "  " });
+        for (i, c) in lines[*age].chars().take(width).enumerate() {
+            // Multi-line entries are shown on one row.
+            let c = if c == '\n' { ' ' } else { c };
+            if positions.contains(&i) {
+                screen.push_str(MATCHED);
+                screen.push(c);
+                screen.push_str(RESET);
+                screen.push_str(if row == selected { SELECTED } else { "" });
+            } else {
+                screen.push(c);
+            }
+        }
+        screen.push_str(RESET);
+    }
+    screen.push_str("\x1b8");
+    let _ = out.write_all(screen.as_bytes());
+    let _ = out.flush();
+}
+
+/// Runs an interactive fuzzy search over history, starting from `query`, and
+/// returns the chosen line, or `None` if the search was cancelled.
+///
+/// Called from a key binding while the terminal is in raw mode; the list is
+/// drawn under the current line and erased again before returning.
+pub fn search(query: &str) -> Option<String> {
+    let lines = history::recent_lines();
+    let mut query = query.to_string();
+    let mut stdout = io::stdout();
+    // Make room for the list first so that scrolling cannot move the saved cursor.
+    let height = VISIBLE_ROWS + 1;
+    let _ = write!(stdout, "{}\x1b[{}A\x1b7", "\r\n".repeat(height), height);
+    let (mut selected, mut top): (usize, usize) = (0, 0);
+    let mut matches = ranked(&query, &lines);
+    let chosen = 'search: loop {
+        // Scroll just far enough to keep the selection visible.
+        top = top.min(selected).max((selected + 1).saturating_sub(VISIBLE_ROWS));
+        draw(&mut stdout, &query, &lines, &matches, selected, top);
+        for key in read_keys() {
+            match key {
+                Key::Char(c) => query.push(c),
+                Key::Backspace => {
+                    query.pop();
+                }
+                Key::Next => {
+                    selected = (selected + 1).min(matches.len().saturating_sub(1));
+                    continue;
+                }
+                Key::Previous => {
+                    selected = selected.saturating_sub(1);
+                    continue;
+                }
+                Key::Accept => break 'search matches.get(selected).map(|(age, _)| lines[*age].clone()),
+                Key::Cancel => break 'search None,
+                Key::Other => continue,
+            }
+            matches = ranked(&query, &lines);
+            selected = 0;
+            top = 0;
+        }
+    };
+    let _ = write!(stdout, "\x1b8\r\n\x1b[J\x1b8");
+    let _ = stdout.flush();
+    chosen
+}
+
+/// Remembers the line the editor should be restarted with.
+pub fn set_chosen(line: String) {
+    *CHOSEN.lock().unwrap() = Some(line);
+}
+
+pub fn take_chosen() -> Option<String> {
+    CHOSEN.lock().unwrap().take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score_of(query: &str, text: &str) -> i64 {
+        score(query, text).map(|(score, _)| score).unwrap_or(i64::MIN)
+    }
+
+    #[test]
+    fn matches_case_insensitive_subsequences() {
+        assert_eq!(score("GS", "git status").map(|(_, positions)| positions), Some(vec![0, 4]));
+        assert_eq!(score("", "anything"), Some((0, Vec::new())));
+        assert_eq!(score("xyz", "git status"), None);
+        assert_eq!(score("sg", "git s"), None);
+    }
+
+    #[test]
+    fn prefers_contiguous_word_start_matches() {
+        assert!(score_of("stat", "git status") > score_of("stat", "s-t-a-t"));
+        assert!(score_of("mk", "make") < score_of("mk", "mk dir"));
+        assert!(score_of("ls", "false ls") > score_of("ls", "false"));
+        // The best of several starting points wins.
+        assert_eq!(score("ab", "a x ab").map(|(_, positions)| positions), Some(vec![4, 5]));
+    }
+
+    #[test]
+    fn ranks_by_score_then_recency() {
+        let lines: Vec<String> = ["cargo build", "echo cb", "cat b", "cargo build"].map(String::from).to_vec();
+        let order: Vec<usize> = ranked("cb", &lines).into_iter().map(|(age, _)| age).collect();
+        assert_eq!(order, [1, 2, 0, 3]);
+        assert!(ranked("zz", &lines).is_empty());
+    }
+}
@@ -0,0 +1,222 @@
+use std::path::Path;
+
+use crate::commands::{self, shopt, type_cmd::can_execute, KEYWORDS};
+use crate::lexer::{self, TokenKind};
+use crate::parser::is_redirect;
+use crate::util::{expand_word, unquoted};
+use crate::vars;
+
+const RESET: &str = "\x1b[0m";
+const COMMAND: &str = "\x1b[32m";
+const UNKNOWN_COMMAND: &str = "\x1b[31m";
+const KEYWORD: &str = "\x1b[1;34m";
+const STRING: &str = "\x1b[33m";
+const VARIABLE: &str = "\x1b[36m";
+const OPERATOR: &str = "\x1b[1m";
+const REDIRECT: &str = "\x1b[35m";
+const COMMENT: &str = "\x1b[90m";
+const UNMATCHED_QUOTE: &str = "\x1b[1;41m";
+const HINT: &str = "\x1b[90m";
+
+/// Colours a command line for display while it is being edited.
+///
+/// The line is split by the same lexer that executes it, so a word is never
+/// shown as something the parser will not treat it as. The command word is
+/// green when it names a builtin, keyword or executable and red otherwise;
+/// quoted strings, expansions, operators, redirections and comments each get
+/// their own colour, and a quote that is never closed is flagged.
+pub fn highlight(line: &str) -> String {
+    // Tokens are classified on the line as it will run, with continuations
+    // joined, and painted over the text as typed.
+    let (joined, offsets) = lexer::join_continuations_mapped(line);
+    let lexed = lexer::tokenize(&joined);
+    let mut out = String::with_capacity(line.len() * 2);
+    let mut last = 0;
+    let mut command_position = true;
+    let mut redirect_target = false;
+    for token in &lexed.tokens {
+        let (start, end) = (offsets[token.start], offsets[token.end]);
+        out.push_str(&line[last..start]);
+        last = end;
+        let word = &joined[token.start..token.end];
+        let text = &line[start..end];
+        match token.kind {
+            TokenKind::Comment => paint(&mut out, COMMENT, text),
+            TokenKind::Operator if is_redirect(text) => {
+                paint(&mut out, REDIRECT, text);
+                redirect_target = true;
+            }
+            TokenKind::Operator => {
+                paint(&mut out, OPERATOR, text);
+                command_position = true;
+            }
+            TokenKind::Word if redirect_target => {
+                paint_word(&mut out, REDIRECT, text);
+                redirect_target = false;
+            }
+            TokenKind::Word if command_position && (KEYWORDS.contains(&word) || word.starts_with("((")) => {
+                paint(&mut out, KEYWORD, text);
+                // Only `!` is followed by another command.
+                command_position = word == "!";
+            }
+            TokenKind::Word if command_position && is_assignment_word(word) => paint_word(&mut out, "", text),
+            TokenKind::Word if command_position => {
+                let style = match resolves(word) {
+                    Some(true) => COMMAND,
+                    Some(false) => UNKNOWN_COMMAND,
+                    None => "",
+                };
+                paint_word(&mut out, style, text);
+                command_position = false;
+            }
+            TokenKind::Word => paint_word(&mut out, "", text),
+        }
+    }
+    out.push_str(&line[last..]);
+    out
+}
+
+/// Greys out an autosuggestion shown after the cursor.
+pub fn highlight_hint(hint: &str) -> String {
+    format!("{}{}{}", HINT, hint, RESET)
+}
+
+fn paint(out: &mut String, style: &str, text: &str) {
+    if style.is_empty() {
+        out.push_str(text);
+    } else {
+        out.push_str(style);
+        out.push_str(text);
+        out.push_str(RESET);
+    }
+}
+
+/// Paints a word in `base`, with its quoted strings and expansions picked out.
+fn paint_word(out: &mut String, base: &str, word: &str) {
+    let bytes = word.as_bytes();
+    let mut plain_start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let (style, end) = match bytes[i] {
+            b'\\' => {
+                i += 2;
+                continue;
+            }
+            b'\'' => match word[i + 1..].find('\'') {
+                Some(offset) => (STRING, i + offset + 2),
+                None => {
+                    // Never closed: flag the opening quote.
+                    paint(out, base, &word[plain_start..i]);
+                    paint(out, UNMATCHED_QUOTE, "'");
+                    i += 1;
+                    plain_start = i;
+                    (STRING, word.len())
+                }
+            },
+            b'"' => {
+                paint(out, base, &word[plain_start..i]);
+                i = paint_double_quoted(out, word, i);
+                plain_start = i;
+                continue;
+            }
+            b'$' => match expansion_end(word, i) {
+                Some(end) => (VARIABLE, end),
+                None => {
+                    i += 1;
+                    continue;
+                }
+            },
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        paint(out, base, &word[plain_start..i]);
+        paint(out, style, &word[i..end]);
+        i = end;
+        plain_start = end;
+    }
+    paint(out, base, &word[plain_start.min(word.len())..]);
+}
+
+/// Paints the double-quoted string starting at `start`, returning where it ends.
+fn paint_double_quoted(out: &mut String, word: &str, start: usize) -> usize {
+    let bytes = word.as_bytes();
+    let mut close = start + 1;
+    while close < bytes.len() && bytes[close] != b'"' {
+        close += if bytes[close] == b'\\' { 2 } else { 1 };
+    }
+    if close >= bytes.len() {
+        // Never closed: flag the opening quote.
+        paint(out, UNMATCHED_QUOTE, "\"");
+        paint(out, STRING, &word[start + 1..]);
+        return word.len();
+    }
+    let mut plain_start = start;
+    let mut i = start + 1;
+    while i < close {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b'$' => match expansion_end(word, i) {
+                Some(end) => {
+                    let end = end.min(close);
+                    paint(out, STRING, &word[plain_start..i]);
+                    paint(out, VARIABLE, &word[i..end]);
+                    i = end;
+                    plain_start = end;
+                }
+                None => i += 1,
+            },
+            _ => i += 1,
+        }
+    }
+    paint(out, STRING, &word[plain_start..=close]);
+    close + 1
+}
+
+/// The end of the `$` expansion at `start`, or `None` for a literal `$`.
+fn expansion_end(word: &str, start: usize) -> Option<usize> {
+    let rest = &word[start + 1..];
+    let first = rest.chars().next()?;
+    let len = match first {
+        '(' | '{' => {
+            let close = if first == '(' { b')' } else { b'}' };
+            let mut depth = 0;
+            rest.bytes()
+                .position(|b| {
+                    if b == first as u8 {
+                        depth += 1;
+                    } else if b == close {
+                        depth -= 1;
+                    }
+                    depth == 0
+                })
+                .map_or(rest.len(), |p| p + 1)
+        }
+        '?' | '$' | '#' | '!' | '@' | '*' | '-' | '0'..='9' => 1,
+        c if c == '_' || c.is_ascii_alphabetic() => {
+            rest.find(|c: char| c != '_' && !c.is_ascii_alphanumeric()).unwrap_or(rest.len())
+        }
+        _ => return None,
+    };
+    Some(start + 1 + len)
+}
+
+/// Whether a command word names something that can be run, or `None` when that
+/// depends on an expansion that cannot be done without side effects. Nothing is
+/// recorded in the hash table: the user has only typed the name.
+fn resolves(word: &str) -> Option<bool> {
+    if word.contains(['$', '`']) {
+        return None;
+    }
+    let name = unquoted(&expand_word(word));
+    Some(
+        commands::lookup(&name).is_some()
+            || can_execute(&name)
+            || (shopt::is_enabled("autocd") && Path::new(&name).is_dir()),
+    )
+}
+
+fn is_assignment_word(word: &str) -> bool {
+    vars::parse_assignment(word).is_some()
+}
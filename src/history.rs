@@ -0,0 +1,212 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::{LazyLock, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::vars;
+
+/// How many entries the log keeps when neither `HISTFILESIZE` nor `HISTSIZE` is set, as in bash.
+const DEFAULT_LOG_SIZE: usize = 500;
+
+/// A command line that was run, with where, when and how it ran. Entries
+/// imported from a plain history file only know their line.
+#[derive(Clone, Default)]
+pub struct Entry {
+    pub line: String,
+    pub cwd: Option<String>,
+    /// When the command started, in seconds since the epoch.
+    pub start: Option<u64>,
+    pub duration_ms: Option<u64>,
+    pub status: Option<i32>,
+    /// Which shell process ran it; see [`session_id`].
+    pub session: Option<String>,
+}
+
+/// Every entry of the history, oldest first.
+static ENTRIES: Mutex<Vec<Entry>> = Mutex::new(Vec::new());
+
+/// Where finished entries are appended, once [`open`] has been called.
+static LOG_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+static SESSION: LazyLock<String> = LazyLock::new(|| format!("{}-{}", process::id(), now()));
+
+pub fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Identifies this shell among others sharing the log: its pid and start time.
+pub fn session_id() -> &'static str {
+    &SESSION
+}
+
+/// Loads the structured log at `path` and appends to it from now on. Returns
+/// false if there was nothing to load.
+///
+/// Each line holds the start time, duration in milliseconds, exit status,
+/// session, directory and command, separated by tabs; the directory and
+/// command escape backslashes, tabs and newlines.
+pub fn open(path: &Path) -> bool {
+    let contents = fs::read_to_string(path).unwrap_or_default();
+    let loaded: Vec<Entry> = contents.lines().filter_map(parse_record).collect();
+    *LOG_PATH.lock().unwrap() = Some(path.to_path_buf());
+    let found = !loaded.is_empty();
+    ENTRIES.lock().unwrap().extend(loaded);
+    found
+}
+
+fn parse_record(record: &str) -> Option<Entry> {
+    let mut fields = record.splitn(6, '\t');
+    let start = fields.next()?.parse().ok()?;
+    let duration_ms = fields.next()?.parse().ok()?;
+    let status = fields.next()?.parse().ok()?;
+    let session = fields.next()?.to_string();
+    let cwd = unescape(fields.next()?);
+    let line = unescape(fields.next()?);
+    Some(Entry {
+        line,
+        cwd: Some(cwd),
+        start: Some(start),
+        duration_ms: Some(duration_ms),
+        status: Some(status),
+        session: Some(session),
+    })
+}
+
+fn escape(field: &str) -> String {
+    field.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n")
+}
+
+fn unescape(field: &str) -> String {
+    let mut out = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        match (c, chars.clone().next()) {
+            ('\\', Some('t')) => out.push('\t'),
+            ('\\', Some('n')) => out.push('\n'),
+            ('\\', Some('\\')) => out.push('\\'),
+            _ => {
+                out.push(c);
+                continue;
+            }
+        }
+        chars.next();
+    }
+    out
+}
+
+/// Adds an entry. A command that is about to run is added before it starts, so
+/// that it shows up in its own `history`, and written out by [`finish`].
+pub fn record(entry: Entry) {
+    ENTRIES.lock().unwrap().push(entry);
+}
+
+/// Fills in how the most recent entry ended and appends it to the log.
+pub fn finish(duration_ms: u64, status: i32) {
+    let mut entries = ENTRIES.lock().unwrap();
+    let Some(entry) = entries.last_mut() else { return };
+    entry.duration_ms = Some(duration_ms);
+    entry.status = Some(status);
+    if let (Some(path), Some(record)) = (LOG_PATH.lock().unwrap().as_ref(), format_record(entry)) {
+        if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+            let _ = file.write_all(record.as_bytes());
+        }
+    }
+}
+
+/// How many entries the log keeps: `$HISTFILESIZE`, else `$HISTSIZE`, else
+/// [`DEFAULT_LOG_SIZE`]. A value that is not a number leaves it untrimmed.
+fn log_limit() -> Option<usize> {
+    match ["HISTFILESIZE", "HISTSIZE"].iter().find_map(|name| vars::get(name).filter(|value| !value.is_empty())) {
+        Some(value) => value.parse().ok(),
+        None => Some(DEFAULT_LOG_SIZE),
+    }
+}
+
+/// Cuts the log down to its newest entries, as bash truncates its history file on exit.
+pub fn trim_log() {
+    let Some(limit) = log_limit() else { return };
+    let Some(path) = LOG_PATH.lock().unwrap().clone() else { return };
+    let Ok(contents) = fs::read_to_string(&path) else { return };
+    let records: Vec<&str> = contents.lines().collect();
+    if records.len() <= limit {
+        return;
+    }
+    let kept: String = records[records.len() - limit..].iter().map(|record| format!("{}\n", record)).collect();
+    let _ = fs::write(&path, kept);
+}
+
+fn format_record(entry: &Entry) -> Option<String> {
+    Some(format!(
+        "{}\t{}\t{}\t{}\t{}\t{}\n",
+        entry.start?,
+        entry.duration_ms?,
+        entry.status?,
+        entry.session.as_ref()?,
+        escape(entry.cwd.as_ref()?),
+        escape(&entry.line)
+    ))
+}
+
+/// A copy of every entry, oldest first.
+pub fn entries() -> Vec<Entry> {
+    ENTRIES.lock().unwrap().clone()
+}
+
+/// The rest of the most recent single-line entry that extends `prefix`,
+/// preferring one that was run in `cwd`.
+pub fn suggest(prefix: &str, cwd: &str) -> Option<String> {
+    if prefix.is_empty() {
+        return None;
+    }
+    let entries = ENTRIES.lock().unwrap();
+    let mut matching = entries
+        .iter()
+        .rev()
+        .filter(|entry| entry.line.len() > prefix.len() && entry.line.starts_with(prefix) && !entry.line.contains('\n'));
+    let best = matching.clone().find(|entry| entry.cwd.as_deref() == Some(cwd)).or_else(|| matching.next())?;
+    Some(best.line[prefix.len()..].to_string())
+}
+
+/// Every distinct line, most recent first.
+pub fn recent_lines() -> Vec<String> {
+    let entries = ENTRIES.lock().unwrap();
+    let mut seen = std::collections::HashSet::new();
+    entries.iter().rev().filter(|entry| seen.insert(entry.line.as_str())).map(|entry| entry.line.clone()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_fields_reversibly() {
+        let field = "a\tb\nc\\t d\\";
+        assert_eq!(escape(field), "a\\tb\\nc\\\\t d\\\\");
+        assert_eq!(unescape(&escape(field)), field);
+        assert_eq!(unescape("a\\qb\\"), "a\\qb\\");
+    }
+
+    #[test]
+    fn records_round_trip() {
+        let entry = Entry {
+            line: "echo 'a\tb'\nls".to_string(),
+            cwd: Some("/tmp/with\ttab".to_string()),
+            start: Some(1_700_000_000),
+            duration_ms: Some(42),
+            status: Some(1),
+            session: Some("123-456".to_string()),
+        };
+        let record = format_record(&entry).unwrap();
+        assert_eq!(record.lines().count(), 1);
+        let parsed = parse_record(record.trim_end_matches('\n')).unwrap();
+        assert_eq!(parsed.line, entry.line);
+        assert_eq!(parsed.cwd, entry.cwd);
+        assert_eq!((parsed.start, parsed.duration_ms, parsed.status), (entry.start, entry.duration_ms, entry.status));
+        assert_eq!(parsed.session, entry.session);
+
+        assert!(format_record(&Entry { line: "ls".to_string(), ..Entry::default() }).is_none());
+        assert!(parse_record("not a record").is_none());
+    }
+}
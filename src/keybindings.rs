@@ -0,0 +1,301 @@
+use std::iter::Peekable;
+use std::str::Chars;
+use std::sync::Mutex;
+
+use rustyline::{Anchor, At, Cmd, EditMode, KeyCode, KeyEvent, Modifiers, Movement, Word};
+
+/// The shell's own editing function: the fuzzy history finder normally on Ctrl-R.
+pub const FUZZY_SEARCH: &str = "fuzzy-history-search";
+
+/// What a key sequence is bound to.
+#[derive(Clone)]
+pub enum Action {
+    /// An editing function, by its readline name.
+    Function(String),
+    /// A shell command, run with `READLINE_LINE` and `READLINE_POINT` describing
+    /// the line being edited (`bind -x`).
+    Command(String),
+}
+
+/// A change for the line editor to pick up before it next prompts.
+pub enum Change {
+    Mode(EditMode),
+    Bind(Vec<KeyEvent>, Action),
+    Unbind(Vec<KeyEvent>),
+}
+
+static PENDING: Mutex<Vec<Change>> = Mutex::new(Vec::new());
+
+/// Bindings made with `bind`, for `bind -p` and `bind -X`.
+static BINDINGS: Mutex<Vec<(Vec<KeyEvent>, Action)>> = Mutex::new(Vec::new());
+
+/// A `bind -x` command waiting to run: the command, the line and the cursor position.
+static COMMAND_REQUEST: Mutex<Option<(String, String, usize)>> = Mutex::new(None);
+
+pub fn set_mode(mode: EditMode) {
+    PENDING.lock().unwrap().push(Change::Mode(mode));
+}
+
+pub fn bind(keyseq: &str, action: Action) -> Result<(), String> {
+    let keys = parse_keyseq(keyseq)?;
+    if let Action::Function(name) = &action {
+        if name != FUZZY_SEARCH && editing_function(name).is_none() {
+            return Err(format!("{}: unknown function name", name));
+        }
+    }
+    let mut bindings = BINDINGS.lock().unwrap();
+    bindings.retain(|(bound, _)| *bound != keys);
+    bindings.push((keys.clone(), action.clone()));
+    PENDING.lock().unwrap().push(Change::Bind(keys, action));
+    Ok(())
+}
+
+pub fn unbind(keyseq: &str) -> Result<(), String> {
+    let keys = parse_keyseq(keyseq)?;
+    BINDINGS.lock().unwrap().retain(|(bound, _)| *bound != keys);
+    PENDING.lock().unwrap().push(Change::Unbind(keys));
+    Ok(())
+}
+
+/// The bindings made so far, in the order they were made, with each key
+/// sequence in the quoted form `bind` reads.
+pub fn bindings() -> Vec<(String, Action)> {
+    BINDINGS.lock().unwrap().iter().map(|(keys, action)| (format_keyseq(keys), action.clone())).collect()
+}
+
+pub fn take_changes() -> Vec<Change> {
+    std::mem::take(&mut *PENDING.lock().unwrap())
+}
+
+pub fn request_command(command: String, line: String, pos: usize) {
+    *COMMAND_REQUEST.lock().unwrap() = Some((command, line, pos));
+}
+
+pub fn take_command_request() -> Option<(String, String, usize)> {
+    COMMAND_REQUEST.lock().unwrap().take()
+}
+
+/// The editing functions `bind` knows, by their readline names.
+fn editing_functions() -> Vec<(&'static str, Cmd)> {
+    vec![
+        ("abort", Cmd::Abort),
+        ("accept-line", Cmd::AcceptLine),
+        ("backward-char", Cmd::Move(Movement::BackwardChar(1))),
+        ("backward-delete-char", Cmd::Kill(Movement::BackwardChar(1))),
+        ("backward-kill-line", Cmd::Kill(Movement::BeginningOfLine)),
+        ("backward-kill-word", Cmd::Kill(Movement::BackwardWord(1, Word::Emacs))),
+        ("backward-word", Cmd::Move(Movement::BackwardWord(1, Word::Emacs))),
+        ("beginning-of-history", Cmd::BeginningOfHistory),
+        ("beginning-of-line", Cmd::Move(Movement::BeginningOfLine)),
+        ("capitalize-word", Cmd::CapitalizeWord),
+        ("clear-screen", Cmd::ClearScreen),
+        ("complete", Cmd::Complete),
+        ("complete-hint", Cmd::CompleteHint),
+        ("delete-char", Cmd::Kill(Movement::ForwardChar(1))),
+        ("downcase-word", Cmd::DowncaseWord),
+        ("end-of-history", Cmd::EndOfHistory),
+        ("end-of-line", Cmd::Move(Movement::EndOfLine)),
+        ("forward-char", Cmd::Move(Movement::ForwardChar(1))),
+        ("forward-search-history", Cmd::ForwardSearchHistory),
+        ("forward-word", Cmd::Move(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs))),
+        ("history-search-backward", Cmd::HistorySearchBackward),
+        ("history-search-forward", Cmd::HistorySearchForward),
+        ("kill-line", Cmd::Kill(Movement::EndOfLine)),
+        ("kill-whole-line", Cmd::Kill(Movement::WholeLine)),
+        ("kill-word", Cmd::Kill(Movement::ForwardWord(1, At::AfterEnd, Word::Emacs))),
+        ("next-history", Cmd::NextHistory),
+        ("previous-history", Cmd::PreviousHistory),
+        ("quoted-insert", Cmd::QuotedInsert),
+        ("reverse-search-history", Cmd::ReverseSearchHistory),
+        ("tab-insert", Cmd::Insert(1, "\t".to_string())),
+        ("transpose-chars", Cmd::TransposeChars),
+        ("transpose-words", Cmd::TransposeWords(1)),
+        ("undo", Cmd::Undo(1)),
+        ("unix-line-discard", Cmd::Kill(Movement::BeginningOfLine)),
+        ("unix-word-rubout", Cmd::Kill(Movement::BackwardWord(1, Word::Big))),
+        ("upcase-word", Cmd::UpcaseWord),
+        ("yank", Cmd::Yank(1, Anchor::Before)),
+        ("yank-pop", Cmd::YankPop),
+    ]
+}
+
+pub fn editing_function(name: &str) -> Option<Cmd> {
+    editing_functions().into_iter().find(|(function, _)| *function == name).map(|(_, cmd)| cmd)
+}
+
+/// Every function name `bind` accepts, sorted.
+pub fn function_names() -> Vec<&'static str> {
+    let mut names: Vec<&str> = editing_functions().iter().map(|(name, _)| *name).collect();
+    names.push(FUZZY_SEARCH);
+    names.sort();
+    names
+}
+
+/// Parses a readline key sequence: either quoted, as in `"\C-x\C-e"`, `"\ef"`
+/// or `"\e[A"`, or a key name such as `Control-u`, `M-f` or `Tab`.
+pub fn parse_keyseq(keyseq: &str) -> Result<Vec<KeyEvent>, String> {
+    let keys = match keyseq.strip_prefix('"').and_then(|inner| inner.strip_suffix('"')) {
+        Some(quoted) => parse_quoted(quoted),
+        None => parse_key_name(keyseq).map(|key| vec![key]),
+    };
+    keys.filter(|keys| !keys.is_empty()).ok_or_else(|| format!("{}: invalid key sequence", keyseq))
+}
+
+fn parse_quoted(text: &str) -> Option<Vec<KeyEvent>> {
+    let mut keys = Vec::new();
+    let mut chars = text.chars().peekable();
+    while chars.peek().is_some() {
+        if chars.clone().take(2).collect::<String>() == "\\e" {
+            chars.nth(1);
+            // Terminals send an escape sequence for each special key.
+            let rest: String = chars.clone().collect();
+            if let Some((sequence, code)) = SPECIAL_KEYS.iter().find(|(sequence, _)| rest.starts_with(sequence)) {
+                chars.nth(sequence.len() - 1);
+                keys.push(KeyEvent(*code, Modifiers::NONE));
+            } else if chars.peek().is_some() {
+                // ESC followed by a key is how Meta arrives.
+                keys.push(KeyEvent::new(key_char(&mut chars)?, Modifiers::ALT));
+            } else {
+                keys.push(KeyEvent(KeyCode::Esc, Modifiers::NONE));
+            }
+        } else if chars.clone().take(3).collect::<String>() == "\\M-" {
+            chars.nth(2);
+            keys.push(KeyEvent::new(key_char(&mut chars)?, Modifiers::ALT));
+        } else {
+            keys.push(KeyEvent::new(key_char(&mut chars)?, Modifiers::NONE));
+        }
+    }
+    Some(keys)
+}
+
+/// Escape sequences of special keys, after the leading ESC.
+const SPECIAL_KEYS: &[(&str, KeyCode)] = &[
+    ("[A", KeyCode::Up),
+    ("OA", KeyCode::Up),
+    ("[B", KeyCode::Down),
+    ("OB", KeyCode::Down),
+    ("[C", KeyCode::Right),
+    ("OC", KeyCode::Right),
+    ("[D", KeyCode::Left),
+    ("OD", KeyCode::Left),
+    ("[H", KeyCode::Home),
+    ("OH", KeyCode::Home),
+    ("[F", KeyCode::End),
+    ("OF", KeyCode::End),
+    ("[2~", KeyCode::Insert),
+    ("[3~", KeyCode::Delete),
+    ("[5~", KeyCode::PageUp),
+    ("[6~", KeyCode::PageDown),
+];
+
+/// Reads one key of a quoted sequence as the character the terminal sends for it.
+fn key_char(chars: &mut Peekable<Chars>) -> Option<char> {
+    match chars.next()? {
+        '\\' => match chars.next()? {
+            'C' if chars.next_if_eq(&'-').is_some() => control(key_char(chars)?),
+            'a' => Some('\x07'),
+            'd' => Some('\x7f'),
+            'e' => Some('\x1b'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            c => Some(c),
+        },
+        c => Some(c),
+    }
+}
+
+fn control(c: char) -> Option<char> {
+    match c {
+        '?' => Some('\x7f'),
+        c if c.is_ascii() => Some((c.to_ascii_uppercase() as u8 & 0x1f) as char),
+        _ => None,
+    }
+}
+
+fn parse_key_name(name: &str) -> Option<KeyEvent> {
+    let (mut rest, mut ctrl, mut meta) = (name, false, false);
+    loop {
+        if let Some(after) = rest.strip_prefix("Control-").or_else(|| rest.strip_prefix("C-")) {
+            (rest, ctrl) = (after, true);
+        } else if let Some(after) = rest.strip_prefix("Meta-").or_else(|| rest.strip_prefix("M-")) {
+            (rest, meta) = (after, true);
+        } else {
+            break;
+        }
+    }
+    let mut chars = rest.chars();
+    let c = match (chars.next()?, chars.next()) {
+        (c, None) => c,
+        _ => match rest.to_ascii_lowercase().as_str() {
+            "del" | "rubout" => '\x7f',
+            "esc" | "escape" => '\x1b',
+            "lfd" | "newline" => '\n',
+            "ret" | "return" => '\r',
+            "space" | "spc" => ' ',
+            "tab" => '\t',
+            _ => return None,
+        },
+    };
+    let c = if ctrl { control(c)? } else { c };
+    Some(KeyEvent::new(c, if meta { Modifiers::ALT } else { Modifiers::NONE }))
+}
+
+fn format_keyseq(keys: &[KeyEvent]) -> String {
+    let mut out = String::from("\"");
+    for KeyEvent(code, mods) in keys {
+        if mods.contains(Modifiers::ALT) {
+            out.push_str("\\e");
+        }
+        if mods.contains(Modifiers::CTRL) {
+            out.push_str("\\C-");
+        }
+        match code {
+            KeyCode::Char(c) if mods.contains(Modifiers::CTRL) => out.push(c.to_ascii_lowercase()),
+            KeyCode::Char(c @ ('"' | '\\')) => {
+                out.push('\\');
+                out.push(*c);
+            }
+            KeyCode::Char(c) => out.push(*c),
+            KeyCode::Backspace => out.push_str("\\C-?"),
+            KeyCode::Enter => out.push_str("\\C-m"),
+            KeyCode::Esc => out.push_str("\\e"),
+            KeyCode::Tab => out.push_str("\\C-i"),
+            code => match SPECIAL_KEYS.iter().find(|(_, special)| special == code) {
+                Some((sequence, _)) => {
+                    out.push_str("\\e");
+                    out.push_str(sequence);
+                }
+                None => out.push('?'),
+            },
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_sequences_and_key_names() {
+        assert_eq!(parse_keyseq("\"\\C-x\\C-e\""), Ok(vec![KeyEvent::ctrl('X'), KeyEvent::ctrl('E')]));
+        assert_eq!(parse_keyseq("\"\\ef\""), Ok(vec![KeyEvent::alt('f')]));
+        assert_eq!(parse_keyseq("\"\\M-b\""), Ok(vec![KeyEvent::alt('b')]));
+        assert_eq!(parse_keyseq("\"\\e[A\""), Ok(vec![KeyEvent(KeyCode::Up, Modifiers::NONE)]));
+        assert_eq!(parse_keyseq("\"ab\""), Ok(vec![KeyEvent::from('a'), KeyEvent::from('b')]));
+        assert_eq!(parse_keyseq("Control-u"), Ok(vec![KeyEvent::ctrl('U')]));
+        assert_eq!(parse_keyseq("M-C-h"), Ok(vec![KeyEvent::new('\x08', Modifiers::ALT)]));
+        assert_eq!(parse_keyseq("Tab"), Ok(vec![KeyEvent(KeyCode::Tab, Modifiers::NONE)]));
+        assert_eq!(parse_keyseq("\"\""), Err("\"\": invalid key sequence".to_string()));
+        assert_eq!(parse_keyseq("Hyper-x"), Err("Hyper-x: invalid key sequence".to_string()));
+    }
+
+    #[test]
+    fn formats_sequences_back_to_readline_syntax() {
+        for keyseq in ["\"\\C-x\\C-e\"", "\"\\ef\"", "\"\\e[A\"", "\"a\\\"\""] {
+            assert_eq!(format_keyseq(&parse_keyseq(keyseq).unwrap()), keyseq);
+        }
+    }
+}
@@ -0,0 +1,330 @@
+/// The lexical category of a token.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A word, still carrying its quotes; expansion happens later.
+    Word,
+    /// A control or redirection operator such as `&&`, `;`, `2>>` or a newline.
+    Operator,
+    /// A `#` comment running to the end of the line.
+    Comment,
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub text: String,
+    /// Byte offsets of the token in the input line.
+    pub start: usize,
+    pub end: usize,
+}
+
+/// The tokens of a line, plus anything left open at its end.
+#[derive(Debug, Default)]
+pub struct Lexed {
+    pub tokens: Vec<Token>,
+    /// The quote character of a quoted string that was never closed.
+    pub unterminated_quote: Option<char>,
+    /// Whether the input ended in an unescaped backslash.
+    pub trailing_backslash: bool,
+    /// Whether a `$(`, `${` or `((` group was never closed.
+    pub unclosed_group: bool,
+}
+
+/// Characters that end a word and start an operator.
+fn is_operator_start(c: char) -> bool {
+    matches!(c, ';' | '&' | '|' | '<' | '>' | '(' | ')' | '\n')
+}
+
+/// Splits a command line into words and operators.
+///
+/// Words keep their quotes and `$` expressions untouched so that quote removal
+/// and expansion can be done later with full knowledge of what was quoted.
+/// Operators are `;`, `&&`, `||`, `(`, `)`, newlines and the redirections
+/// `<`, `>`, `>>`, `>|`, `<&` and `>&`, optionally preceded by a file descriptor number.
+/// A lone `|` or `&` is still an ordinary word character. In the assignments
+/// that start a command, the subscript of `name[subscript]=value` may hold spaces.
+pub fn tokenize(input: &str) -> Lexed {
+    let mut lexed = Lexed::default();
+    let mut chars = input.char_indices().peekable();
+    let mut word_start: Option<usize> = None;
+    // Whether the next word could be an assignment: only assignments precede it in its command.
+    let mut assignment_position = true;
+
+    let finish_word = |lexed: &mut Lexed, start: &mut Option<usize>, assignment_position: &mut bool, end: usize| {
+        if let Some(start) = start.take() {
+            let text = input[start..end].to_string();
+            *assignment_position &= crate::vars::parse_assignment(&text).is_some();
+            lexed.tokens.push(Token { kind: TokenKind::Word, text, start, end });
+        }
+    };
+
+    while let Some(&(i, c)) = chars.peek() {
+        match c {
+            '\'' | '"' => {
+                word_start.get_or_insert(i);
+                chars.next();
+                let mut closed = false;
+                while let Some((_, q)) = chars.next() {
+                    if q == c {
+                        closed = true;
+                        break;
+                    }
+                    if c == '"' && q == '\\' {
+                        chars.next();
+                    }
+                }
+                if !closed {
+                    lexed.unterminated_quote = Some(c);
+                }
+            }
+            '\\' => {
+                word_start.get_or_insert(i);
+                chars.next();
+                if chars.next().is_none() {
+                    lexed.trailing_backslash = true;
+                }
+            }
+            '$' if matches!(input[i + 1..].chars().next(), Some('(' | '{')) => {
+                // `$(( ))` and `${ }` may hold operators and spaces; keep them in the word.
+                word_start.get_or_insert(i);
+                chars.next();
+                lexed.unclosed_group |= !skip_balanced(&mut chars);
+            }
+            '(' if word_start.is_some_and(|ws| is_assignment_prefix(&input[ws..i])) => {
+                // The value of a compound assignment `arr=(a b c)` is part of the word.
+                lexed.unclosed_group |= !skip_balanced(&mut chars);
+            }
+            '[' if assignment_position && word_start.is_some_and(|ws| crate::vars::is_valid_name(&input[ws..i])) => {
+                let end = subscript_end(&input[i..]).map_or(i + 1, |len| i + len);
+                while chars.peek().is_some_and(|&(j, _)| j < end) {
+                    chars.next();
+                }
+            }
+            '(' if word_start.is_none() && input[i..].starts_with("((") => {
+                // An arithmetic command `(( expr ))` is kept whole as a single word.
+                word_start = Some(i);
+                lexed.unclosed_group |= !skip_balanced(&mut chars);
+            }
+            '#' if word_start.is_none() => {
+                let text = &input[i..];
+                let end = i + text.find('\n').unwrap_or(text.len());
+                lexed.tokens.push(Token { kind: TokenKind::Comment, text: input[i..end].to_string(), start: i, end });
+                while chars.peek().is_some_and(|&(j, _)| j < end) {
+                    chars.next();
+                }
+            }
+            c if c.is_whitespace() && c != '\n' => {
+                finish_word(&mut lexed, &mut word_start, &mut assignment_position, i);
+                chars.next();
+            }
+            c if is_operator_start(c) => {
+                let next = input[i + c.len_utf8()..].chars().next();
+                let len = match (c, next) {
+                    ('&', Some('&')) | ('|', Some('|')) | ('>', Some('>' | '&' | '|')) | ('<', Some('&')) => 2,
+                    // Single `&` and `|` are not operators (yet); keep them in the word.
+                    ('&' | '|', _) => 0,
+                    _ => 1,
+                };
+                if len == 0 {
+                    word_start.get_or_insert(i);
+                    chars.next();
+                    continue;
+                }
+                // A word made only of digits directly before `<` or `>` is a file descriptor.
+                let mut start = i;
+                if matches!(c, '<' | '>') {
+                    if let Some(ws) = word_start {
+                        if input[ws..i].bytes().all(|b| b.is_ascii_digit()) {
+                            start = ws;
+                            word_start = None;
+                        }
+                    }
+                }
+                finish_word(&mut lexed, &mut word_start, &mut assignment_position, i);
+                for _ in 0..len {
+                    chars.next();
+                }
+                let end = i + len;
+                // A new command starts after anything but a redirection.
+                if !matches!(c, '<' | '>') {
+                    assignment_position = true;
+                }
+                lexed.tokens.push(Token { kind: TokenKind::Operator, text: input[start..end].to_string(), start, end });
+            }
+            _ => {
+                word_start.get_or_insert(i);
+                chars.next();
+            }
+        }
+    }
+    finish_word(&mut lexed, &mut word_start, &mut assignment_position, input.len());
+    lexed
+}
+
+/// The length of a subscript `[...]` at the start of `text`, up to and
+/// including its `]`, if an assignment operator follows it.
+fn subscript_end(text: &str) -> Option<usize> {
+    let mut chars = text.char_indices();
+    let mut depth = 0;
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '[' => depth += 1,
+            ']' => {
+                depth -= 1;
+                if depth == 0 {
+                    let rest = &text[i + 1..];
+                    return (rest.starts_with('=') || rest.starts_with("+=")).then_some(i + 1);
+                }
+            }
+            '\\' => {
+                chars.next();
+            }
+            '\'' | '"' => {
+                while let Some((_, q)) = chars.next() {
+                    if q == c {
+                        break;
+                    }
+                    if c == '"' && q == '\\' {
+                        chars.next();
+                    }
+                }
+            }
+            '\n' => return None,
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Whether `word` is `NAME=` or `NAME+=`, which may be followed by an array value.
+pub fn is_assignment_prefix(word: &str) -> bool {
+    let name = word.strip_suffix("+=").or_else(|| word.strip_suffix('=')).unwrap_or_default();
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Consumes a bracketed group starting at the opening `(` or `{`, including any
+/// nested groups and quoted text, up to and including the matching close.
+/// Returns false if the input ends first.
+fn skip_balanced(chars: &mut std::iter::Peekable<std::str::CharIndices>) -> bool {
+    let mut stack = Vec::new();
+    while let Some((_, c)) = chars.next() {
+        match c {
+            '(' => stack.push(')'),
+            '{' => stack.push('}'),
+            ')' | '}' if stack.last() == Some(&c) => {
+                stack.pop();
+                if stack.is_empty() {
+                    return true;
+                }
+            }
+            '\\' => {
+                chars.next();
+            }
+            '\'' | '"' => {
+                while let Some((_, q)) = chars.next() {
+                    if q == c {
+                        break;
+                    }
+                    if c == '"' && q == '\\' {
+                        chars.next();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Removes backslash-newline pairs, which continue a line, except inside single quotes.
+pub fn join_continuations(input: &str) -> String {
+    join_continuations_mapped(input).0
+}
+
+/// Like [`join_continuations`], also giving the offset in `input` of each byte
+/// of the result, plus one for its end, so that tokens can be traced back.
+pub fn join_continuations_mapped(input: &str) -> (String, Vec<usize>) {
+    let mut joined = String::with_capacity(input.len());
+    let mut offsets = Vec::with_capacity(input.len() + 1);
+    let mut push = |joined: &mut String, at: usize, c: char| {
+        joined.push(c);
+        offsets.extend((0..c.len_utf8()).map(|i| at + i));
+    };
+    let mut chars = input.char_indices().peekable();
+    let mut quote = None;
+    while let Some((at, c)) = chars.next() {
+        match (c, quote) {
+            ('\\', Some('\'')) => {}
+            ('\\', _) => match chars.next() {
+                Some((_, '\n')) => continue,
+                Some((next_at, next)) => {
+                    push(&mut joined, at, c);
+                    push(&mut joined, next_at, next);
+                    continue;
+                }
+                None => {}
+            },
+            ('\'' | '"', None) => quote = Some(c),
+            (_, Some(q)) if c == q => quote = None,
+            _ => {}
+        }
+        push(&mut joined, at, c);
+    }
+    offsets.push(input.len());
+    (joined, offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts(input: &str) -> Vec<String> {
+        tokenize(input).tokens.into_iter().map(|t| t.text).collect()
+    }
+
+    #[test]
+    fn splits_words_and_operators() {
+        assert_eq!(texts("a && b || c; d"), ["a", "&&", "b", "||", "c", ";", "d"]);
+        assert_eq!(texts("echo 'a b' \"c;d\" e\\ f"), ["echo", "'a b'", "\"c;d\"", "e\\ f"]);
+        assert_eq!(texts("[[ a|b ]]"), ["[[", "a|b", "]]"]);
+        assert_eq!(texts("list=(a 'b c') x+=(d)"), ["list=(a 'b c')", "x+=(d)"]);
+        assert_eq!(texts("echo ${x:-a b} $((1 + 2)) ((i < 3))"), ["echo", "${x:-a b}", "$((1 + 2))", "((i < 3))"]);
+    }
+
+    #[test]
+    fn attaches_descriptors_to_redirections() {
+        assert_eq!(texts("cmd 2>>log >&2 <in"), ["cmd", "2>>", "log", ">&", "2", "<", "in"]);
+        assert_eq!(texts("cmd >|out"), ["cmd", ">|", "out"]);
+        assert_eq!(texts("echo a2>b"), ["echo", "a2", ">", "b"]);
+    }
+
+    #[test]
+    fn keeps_comments_and_reports_what_is_left_open() {
+        use TokenKind::*;
+        let lexed = tokenize("echo a # note\necho b");
+        let kinds: Vec<TokenKind> = lexed.tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(kinds, [Word, Word, Comment, Operator, Word, Word]);
+        assert_eq!(lexed.tokens[2].text, "# note");
+        assert_eq!(texts("echo a#b"), ["echo", "a#b"]);
+
+        assert_eq!(tokenize("echo 'abc").unterminated_quote, Some('\''));
+        assert!(tokenize("echo abc\\").trailing_backslash);
+        assert!(tokenize("echo ${x").unclosed_group);
+        assert!(!tokenize("echo ${x}").unclosed_group);
+    }
+
+    #[test]
+    fn keeps_assignment_subscripts_whole() {
+        assert_eq!(texts("m[two words]=2 n['a b']+=3 cmd"), ["m[two words]=2", "n['a b']+=3", "cmd"]);
+        assert_eq!(texts("echo m[two words]=2"), ["echo", "m[two", "words]=2"]);
+        assert_eq!(texts("m[a b] = 2"), ["m[a", "b]", "=", "2"]);
+        assert_eq!(texts("a=1; m[x y]=2"), ["a=1", ";", "m[x y]=2"]);
+    }
+
+    #[test]
+    fn joins_continuations_outside_single_quotes() {
+        assert_eq!(join_continuations("ec\\\nho '\\\n'"), "echo '\\\n'");
+    }
+}
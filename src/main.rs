@@ -1,6 +1,18 @@
 mod shell;
 mod commands;
+mod completers;
+mod arith;
+mod braces;
 mod util;
+mod vars;
+mod lexer;
+mod parser;
+mod pattern;
+mod highlight;
+mod history;
+mod fuzzy;
+mod keybindings;
+mod traps;
 
 fn main() {
     shell::start_shell();
@@ -0,0 +1,303 @@
+use crate::lexer::{Lexed, Token, TokenKind};
+
+/// How a pipeline is joined to the one before it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Connector {
+    /// `;`, a newline, or the start of the line.
+    Seq,
+    /// `&&`: run only if the previous status was zero.
+    And,
+    /// `||`: run only if the previous status was non-zero.
+    Or,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RedirectOp {
+    /// `<`
+    Read,
+    /// `>`
+    Write,
+    /// `>>`
+    Append,
+    /// `>|`: write even if `set -C` forbids overwriting.
+    Clobber,
+    /// `>&` / `<&`: duplicate another descriptor.
+    Duplicate,
+}
+
+#[derive(Clone, Debug)]
+pub struct Redirect {
+    pub fd: i32,
+    pub op: RedirectOp,
+    /// The unexpanded target word: a filename, or a descriptor number for `>&`.
+    pub target: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SimpleCommand {
+    /// Unexpanded words, quotes included.
+    pub words: Vec<String>,
+    pub redirects: Vec<Redirect>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Command {
+    Simple(SimpleCommand),
+    /// A `[[ ... ]]` expression; holds the unexpanded words between the brackets.
+    Conditional(Vec<String>),
+    /// An `(( ... ))` arithmetic command; holds the unexpanded expression.
+    Arithmetic(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    /// Set by a leading `!`, which inverts the status.
+    pub negated: bool,
+    pub command: Command,
+}
+
+pub type List = Vec<(Connector, Pipeline)>;
+
+/// A syntax error, carrying the token the parser choked on.
+#[derive(Debug)]
+pub struct SyntaxError(pub String);
+
+impl std::fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let token = if self.0 == "\n" { "newline" } else { &self.0 };
+        write!(f, "syntax error near unexpected token `{}'", token)
+    }
+}
+
+/// Builds a command list out of the lexer's tokens. Comments are dropped.
+pub fn parse(tokens: &[Token]) -> Result<List, SyntaxError> {
+    let mut tokens = tokens.iter().filter(|t| t.kind != TokenKind::Comment).peekable();
+    let mut list = List::new();
+    let mut connector = Connector::Seq;
+    loop {
+        // Skip empty commands between separators, e.g. a blank line. A newline may
+        // also follow `&&` or `||`.
+        while tokens.peek().is_some_and(|t| is_separator(t) && (connector == Connector::Seq || t.text == "\n")) {
+            tokens.next();
+        }
+        let Some(first) = tokens.peek() else { break };
+        if first.kind == TokenKind::Operator && !is_redirect(&first.text) {
+            return Err(SyntaxError(first.text.clone()));
+        }
+
+        let mut negated = false;
+        while tokens.peek().is_some_and(|t| t.kind == TokenKind::Word && t.text == "!") {
+            tokens.next();
+            negated = !negated;
+        }
+
+        let arithmetic = tokens.peek().and_then(|t| t.text.strip_prefix("((")?.strip_suffix("))"));
+        let command = if let Some(expression) = arithmetic {
+            let expression = expression.to_string();
+            tokens.next();
+            Command::Arithmetic(expression)
+        } else if tokens.peek().is_some_and(|t| t.kind == TokenKind::Word && t.text == "[[") {
+            tokens.next();
+            let mut words = Vec::new();
+            loop {
+                match tokens.next() {
+                    Some(t) if t.kind == TokenKind::Word && t.text == "]]" => break,
+                    // A regex may contain `(`, `)` and `|`; glue its pieces back together
+                    // as long as no whitespace separates them.
+                    Some(t) if !is_separator(t) && words.last().is_some_and(|w| w == "=~") => {
+                        let mut word = t.text.clone();
+                        let mut end = t.end;
+                        while let Some(next) = tokens.next_if(|n| n.start == end && !is_separator(n)) {
+                            word.push_str(&next.text);
+                            end = next.end;
+                        }
+                        words.push(word);
+                    }
+                    // Inside [[ ]], `<`, `>`, `(`, `&&` and friends are just operands.
+                    Some(t) if !is_separator(t) => words.push(t.text.clone()),
+                    // The expression may go on over several lines.
+                    Some(t) if t.text == "\n" => {}
+                    Some(t) => return Err(SyntaxError(t.text.clone())),
+                    None => return Err(SyntaxError("newline".to_string())),
+                }
+            }
+            Command::Conditional(words)
+        } else {
+            let mut simple = SimpleCommand::default();
+            while let Some(token) = tokens.peek() {
+                if token.kind == TokenKind::Word {
+                    simple.words.push(token.text.clone());
+                    tokens.next();
+                } else if is_redirect(&token.text) {
+                    let op = tokens.next().map(|t| t.text.clone()).unwrap_or_default();
+                    match tokens.next() {
+                        Some(target) if target.kind == TokenKind::Word => {
+                            simple.redirects.push(parse_redirect(&op, target.text.clone()));
+                        }
+                        Some(other) => return Err(SyntaxError(other.text.clone())),
+                        None => return Err(SyntaxError("newline".to_string())),
+                    }
+                } else {
+                    break;
+                }
+            }
+            if simple.words.is_empty() && simple.redirects.is_empty() {
+                let next = tokens.peek().map_or("newline".to_string(), |t| t.text.clone());
+                return Err(SyntaxError(next));
+            }
+            Command::Simple(simple)
+        };
+        list.push((connector, Pipeline { negated, command }));
+
+        match tokens.next() {
+            None => break,
+            Some(t) if t.text == "&&" => connector = Connector::And,
+            Some(t) if t.text == "||" => connector = Connector::Or,
+            Some(t) if is_separator(t) => connector = Connector::Seq,
+            Some(t) => return Err(SyntaxError(t.text.clone())),
+        }
+        if connector != Connector::Seq && tokens.peek().is_none() {
+            return Err(SyntaxError("newline".to_string()));
+        }
+    }
+    Ok(list)
+}
+
+fn is_separator(token: &Token) -> bool {
+    token.kind == TokenKind::Operator && matches!(token.text.as_str(), ";" | "\n")
+}
+
+/// Whether an operator is a redirection, with or without a leading descriptor number.
+pub fn is_redirect(op: &str) -> bool {
+    let op = op.trim_start_matches(|c: char| c.is_ascii_digit());
+    matches!(op, "<" | ">" | ">>" | ">|" | "<&" | ">&")
+}
+
+fn parse_redirect(op: &str, target: String) -> Redirect {
+    let digits_end = op.find(|c: char| !c.is_ascii_digit()).unwrap_or(op.len());
+    let (fd, op) = op.split_at(digits_end);
+    let default_fd = if op.starts_with('<') { 0 } else { 1 };
+    let fd = fd.parse().unwrap_or(default_fd);
+    let op = match op {
+        "<" => RedirectOp::Read,
+        ">>" => RedirectOp::Append,
+        ">|" => RedirectOp::Clobber,
+        "<&" | ">&" => RedirectOp::Duplicate,
+        _ => RedirectOp::Write,
+    };
+    Redirect { fd, op, target }
+}
+
+/// Whether the input stops partway through a command and should continue on the
+/// next line: inside a quote or a `$(`/`${`/`((` group, after a trailing `\`,
+/// `|`, `&&` or `||`, or within an `if`, loop, `case`, `{`, `(` or `[[` that has
+/// not been closed yet.
+pub fn is_incomplete(lexed: &Lexed) -> bool {
+    if lexed.unterminated_quote.is_some() || lexed.trailing_backslash || lexed.unclosed_group {
+        return true;
+    }
+    let tokens: Vec<&Token> = lexed.tokens.iter().filter(|t| t.kind != TokenKind::Comment).collect();
+    if tokens.iter().rev().find(|t| t.text != "\n").is_some_and(|t| matches!(t.text.as_str(), "|" | "&&" | "||")) {
+        return true;
+    }
+    // Open compound commands, and open parentheses.
+    let (mut depth, mut parens) = (0, 0);
+    let mut command_position = true;
+    let mut in_conditional = false;
+    for token in tokens {
+        let text = token.text.as_str();
+        if in_conditional {
+            in_conditional = text != "]]";
+            continue;
+        }
+        if token.kind == TokenKind::Operator {
+            match text {
+                "(" => parens += 1,
+                ")" => parens -= 1,
+                _ => {}
+            }
+            command_position = !is_redirect(text);
+            continue;
+        }
+        if command_position {
+            match text {
+                "if" | "case" | "for" | "select" | "while" | "until" | "{" => depth += 1,
+                "fi" | "esac" | "done" | "}" => depth -= 1,
+                "[[" => in_conditional = true,
+                _ => {}
+            }
+        }
+        // A reserved word in command position is followed by another command.
+        command_position = command_position && matches!(text, "if" | "then" | "else" | "elif" | "do" | "while" | "until" | "{" | "!");
+    }
+    in_conditional || depth > 0 || parens > 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::tokenize;
+
+    fn parse_line(input: &str) -> Result<List, String> {
+        parse(&tokenize(input).tokens).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn joins_pipelines_with_connectors() {
+        let list = parse_line("a && ! b || c; d").unwrap();
+        let connectors: Vec<Connector> = list.iter().map(|(c, _)| *c).collect();
+        assert_eq!(connectors, [Connector::Seq, Connector::And, Connector::Or, Connector::Seq]);
+        assert!(list[1].1.negated);
+        assert!(!list[0].1.negated);
+    }
+
+    #[test]
+    fn collects_redirections() {
+        let list = parse_line("cmd arg 2>>log <in").unwrap();
+        let Command::Simple(simple) = &list[0].1.command else { panic!("not a simple command") };
+        assert_eq!(simple.words, ["cmd", "arg"]);
+        let redirects: Vec<(i32, RedirectOp, &str)> =
+            simple.redirects.iter().map(|r| (r.fd, r.op, r.target.as_str())).collect();
+        assert_eq!(redirects, [(2, RedirectOp::Append, "log"), (0, RedirectOp::Read, "in")]);
+    }
+
+    #[test]
+    fn parses_conditional_and_arithmetic_commands() {
+        let list = parse_line("[[ a =~ ^(x|y)$ && -n b ]]; (( i += 1 ))").unwrap();
+        let Command::Conditional(words) = &list[0].1.command else { panic!("not a conditional") };
+        assert_eq!(words, &["a", "=~", "^(x|y)$", "&&", "-n", "b"]);
+        let Command::Arithmetic(expression) = &list[1].1.command else { panic!("not arithmetic") };
+        assert_eq!(expression, " i += 1 ");
+    }
+
+    #[test]
+    fn reports_the_offending_token() {
+        assert_eq!(parse_line("&& a").unwrap_err(), "syntax error near unexpected token `&&'");
+        assert_eq!(parse_line("a; && b").unwrap_err(), "syntax error near unexpected token `&&'");
+        assert_eq!(parse_line("a >").unwrap_err(), "syntax error near unexpected token `newline'");
+        assert_eq!(parse_line("[[ a ; ]]").unwrap_err(), "syntax error near unexpected token `;'");
+    }
+
+    #[test]
+    fn detects_incomplete_input() {
+        let incomplete = |input: &str| is_incomplete(&tokenize(input));
+        assert!(incomplete("echo 'a\nb"));
+        assert!(incomplete("echo \"a\n"));
+        assert!(incomplete("a &&"));
+        assert!(incomplete("a ||\n"));
+        assert!(incomplete("[[ -n a\n&& -n b"));
+        assert!(incomplete("echo $((1 +"));
+        assert!(incomplete("a |"));
+        assert!(incomplete("if true; then a"));
+        assert!(incomplete("{ a"));
+        assert!(incomplete("( a"));
+        assert!(!incomplete("if true; then a; fi"));
+        assert!(!incomplete("{ a; }"));
+        assert!(!incomplete("[[ a ]] && b"));
+        assert!(!incomplete("[[ -n a\n&& -n b ]]"));
+        assert!(!incomplete("echo 'a\nb'"));
+        assert!(!incomplete("echo [[ a"));
+        assert!(!incomplete("echo if {"));
+        assert!(!incomplete("a # &&"));
+    }
+}
@@ -0,0 +1,130 @@
+/// Whether `text` matches the whole of `pattern`.
+pub fn matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    match_from(&pattern, &text)
+}
+
+fn match_from(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the most recent `*`: (pattern index, text index).
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        let step = match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p + 1, t));
+                p += 1;
+                continue;
+            }
+            Some('?') => Some(1),
+            Some('[') => match match_bracket(&pattern[p..], text[t]) {
+                Some((true, len)) => Some(len),
+                Some((false, _)) => None,
+                // An unclosed `[` is an ordinary character.
+                None => (text[t] == '[').then_some(1),
+            },
+            Some('\\') if p + 1 < pattern.len() => (pattern[p + 1] == text[t]).then_some(2),
+            Some(&c) => (c == text[t]).then_some(1),
+            None => None,
+        };
+        match step {
+            Some(len) => {
+                p += len;
+                t += 1;
+            }
+            None => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Matches `c` against the bracket expression at the start of `pattern`,
+/// returning whether it matched and the expression's length, or `None` if
+/// the bracket is never closed.
+fn match_bracket(pattern: &[char], c: char) -> Option<(bool, usize)> {
+    let mut i = 1;
+    let negated = matches!(pattern.get(i), Some('!' | '^'));
+    if negated {
+        i += 1;
+    }
+    let mut matched = false;
+    let mut first = true;
+    loop {
+        let mut lo = *pattern.get(i)?;
+        if lo == ']' && !first {
+            return Some((matched != negated, i + 1));
+        }
+        first = false;
+        if lo == '\\' {
+            i += 1;
+            lo = *pattern.get(i)?;
+        }
+        i += 1;
+        if pattern.get(i) == Some(&'-') && pattern.get(i + 1).is_some_and(|&hi| hi != ']') {
+            let mut hi = pattern[i + 1];
+            i += 2;
+            if hi == '\\' {
+                hi = *pattern.get(i)?;
+                i += 1;
+            }
+            matched |= lo <= c && c <= hi;
+        } else {
+            matched |= lo == c;
+        }
+    }
+}
+
+/// Turns an expanded word into a pattern in which the quoted characters only
+/// match themselves.
+pub fn escape(word: &[(char, bool)]) -> String {
+    let mut pattern = String::new();
+    for &(c, quoted) in word {
+        if quoted && matches!(c, '*' | '?' | '[' | ']' | '\\') {
+            pattern.push('\\');
+        }
+        pattern.push(c);
+    }
+    pattern
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_wildcards() {
+        assert!(matches("*.rs", "main.rs"));
+        assert!(matches("a*b*c", "aXbYbc"));
+        assert!(matches("?at", "cat"));
+        assert!(!matches("?at", "at"));
+        assert!(matches("*", ""));
+        assert!(!matches("a*", "ba"));
+    }
+
+    #[test]
+    fn matches_bracket_expressions() {
+        assert!(matches("[a-c]x", "bx"));
+        assert!(!matches("[a-c]x", "dx"));
+        assert!(matches("[!a-c]", "d"));
+        assert!(matches("[^a]", "b"));
+        assert!(matches("[]]", "]"));
+        assert!(matches("[ab", "[ab"));
+    }
+
+    #[test]
+    fn escaped_characters_match_literally() {
+        assert!(matches("\\*", "*"));
+        assert!(!matches("\\*", "x"));
+        let word: Vec<(char, bool)> = vec![('*', true), ('.', false), ('?', false)];
+        assert_eq!(escape(&word), "\\*.?");
+        assert!(matches(&escape(&word), "*.c"));
+        assert!(!matches(&escape(&word), "a.c"));
+    }
+}
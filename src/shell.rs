@@ -1,160 +1,395 @@
 use nix::libc;
+use nix::unistd::isatty;
+use std::env;
 use std::io::{self, Write};
-
-// Import your command handlers.
-use crate::commands::echo::handle_echo_command;
-use crate::commands::cd::handle_cd_command;
-use crate::commands::type_cmd::handle_type_command;
-use crate::commands::pwd::handle_pwd_command;
-use crate::commands::execute::handle_execute_command;
-use crate::commands::exit::handle_exit_command;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::time::Instant;
+
+use crate::braces;
+use crate::commands::{self, builtins, Io};
+use crate::commands::cd::logical_cwd;
+use crate::commands::dirs::expand_stack_tilde;
+use crate::commands::execute::run_external;
+use crate::commands::hash;
+use crate::commands::set;
+use crate::commands::shopt;
+use crate::commands::test::eval_conditional;
+use crate::commands::type_cmd::find_executable;
+use crate::completers::complete_argument;
+use crate::fuzzy;
+use crate::highlight;
+use crate::history;
+use crate::keybindings::{self, Action, Change};
+use crate::lexer;
+use crate::parser::{self, Command, Connector, List, Pipeline, Redirect, RedirectOp, SimpleCommand};
+use crate::traps;
+use crate::util::{
+    assign, assign_compound, compound_assignment, evaluate_arithmetic, expand_assignment, expand_word, expansion_failed,
+    parse_parameters, single_quote, take_expansion_error, unquoted,
+};
+use crate::vars;
 
 // --------------------- Redirection Support ---------------------
 
-#[derive(Debug)]
-struct RedirectionSpec {
-    stdout: Option<(String, bool)>, // (filename, append) for stdout
-    stderr: Option<(String, bool)>, // (filename, append) for stderr
-}
+/// Runs `args` with `redirects` applied to the shell's own descriptors, which
+/// are restored afterwards. Builtins and external commands both see the result.
+fn run_command_with_redirections(args: &[String], redirects: &[Redirect]) -> i32 {
+    use std::fs::OpenOptions;
+    use std::os::unix::io::IntoRawFd;
+    use nix::unistd::{dup, dup2, close};
 
-/// Parses the input line into the command part and a redirection specification.
-/// Assumes that redirection tokens (>, >>, 2>, 2>>) are separated by whitespace.
-fn parse_command_and_redirections(input: &str) -> (String, RedirectionSpec) {
-    let mut redir = RedirectionSpec {
-        stdout: None,
-        stderr: None,
-    };
-    let tokens: Vec<&str> = input.split_whitespace().collect();
-    let mut cmd_tokens = Vec::new();
-    let mut i = 0;
-    while i < tokens.len() {
-        match tokens[i] {
-            "2>>" => {
-                if i + 1 < tokens.len() {
-                    redir.stderr = Some((tokens[i + 1].to_string(), true));
-                    i += 2;
-                } else { break; }
-            },
-            "2>" => {
-                if i + 1 < tokens.len() {
-                    redir.stderr = Some((tokens[i + 1].to_string(), false));
-                    i += 2;
-                } else { break; }
-            },
-            "1>>" | ">>" => {
-                if i + 1 < tokens.len() {
-                    redir.stdout = Some((tokens[i + 1].to_string(), true));
-                    i += 2;
-                } else { break; }
-            },
-            "1>" | ">" => {
-                if i + 1 < tokens.len() {
-                    redir.stdout = Some((tokens[i + 1].to_string(), false));
-                    i += 2;
-                } else { break; }
+    // Each touched descriptor and the copy of its original (or -1 if it was closed).
+    let mut saved: Vec<(i32, i32)> = Vec::new();
+    let mut status = None;
+    for redirect in redirects {
+        let target = match parse_parameters(&redirect.target).as_slice() {
+            [target] => target.clone(),
+            _ => {
+                eprintln!("{}: ambiguous redirect", redirect.target);
+                status = Some(1);
+                break;
+            }
+        };
+        let source = match redirect.op {
+            RedirectOp::Duplicate => match target.parse::<i32>() {
+                Ok(fd) => Ok(fd),
+                Err(_) => {
+                    eprintln!("{}: ambiguous redirect", target);
+                    status = Some(1);
+                    break;
+                }
             },
-            token => {
-                cmd_tokens.push(token);
-                i += 1;
+            RedirectOp::Read => std::fs::File::open(&target).map(|file| file.into_raw_fd()),
+            // With `set -C`, `>` will not replace an existing regular file.
+            RedirectOp::Write if set::is_enabled("noclobber") && std::fs::metadata(&target).is_ok_and(|m| m.is_file()) => {
+                eprintln!("{}: cannot overwrite existing file", target);
+                status = Some(1);
+                break;
             }
+            RedirectOp::Write | RedirectOp::Append | RedirectOp::Clobber => {
+                let append = redirect.op == RedirectOp::Append;
+                OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .append(append)
+                    .truncate(!append)
+                    .open(&target)
+                    .map(|file| file.into_raw_fd())
+            }
+        };
+        let source = match source {
+            Ok(fd) => fd,
+            Err(e) => {
+                let message = if e.kind() == io::ErrorKind::NotFound { "No such file or directory".to_string() } else { e.to_string() };
+                eprintln!("{}: {}", target, message);
+                status = Some(1);
+                break;
+            }
+        };
+        if !saved.iter().any(|&(fd, _)| fd == redirect.fd) {
+            saved.push((redirect.fd, dup(redirect.fd).unwrap_or(-1)));
+        }
+        let result = dup2(source, redirect.fd);
+        if redirect.op != RedirectOp::Duplicate {
+            close(source).ok();
+        }
+        if result.is_err() {
+            eprintln!("{}: Bad file descriptor", target);
+            status = Some(1);
+            break;
+        }
+        if redirect.fd == libc::STDIN_FILENO {
+            STDIN_REDIRECTED.store(true, Ordering::Relaxed);
         }
     }
-    (cmd_tokens.join(" "), redir)
+
+    // A failed redirection means the command does not run at all.
+    let status = status.unwrap_or_else(|| run_argv(args));
+
+    io::stdout().flush().ok();
+    io::stderr().flush().ok();
+
+    // Restore the original descriptors, most recently saved first.
+    for (fd, copy) in saved.into_iter().rev() {
+        if copy >= 0 {
+            dup2(copy, fd).ok();
+            close(copy).ok();
+        } else {
+            close(fd).ok();
+        }
+    }
+    STDIN_REDIRECTED.store(false, Ordering::Relaxed);
+    status
 }
 
-/// Executes a command with redirections applied.
-fn run_command_with_redirections(cmd: &str, redir: RedirectionSpec) {
-    use std::fs::OpenOptions;
-    use std::os::unix::io::AsRawFd;
-    use nix::unistd::{dup, dup2, close};
+/// Set while fd 0 points at a `<` redirection rather than the shell's own input.
+static STDIN_REDIRECTED: AtomicBool = AtomicBool::new(false);
+
+pub fn stdin_redirected() -> bool {
+    STDIN_REDIRECTED.load(Ordering::Relaxed)
+}
+
+/// Set while a command typed at the interactive prompt runs, as opposed to one
+/// from a startup file or a `bind -x` key binding.
+static TYPED_COMMAND: AtomicBool = AtomicBool::new(false);
 
-    // Save original stdout and stderr.
-    let stdout_fd = dup(libc::STDOUT_FILENO).expect("dup failed for stdout");
-    let stderr_fd = dup(libc::STDERR_FILENO).expect("dup failed for stderr");
-
-    if let Some((ref file, append)) = redir.stdout {
-        let file_handle = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(append)
-            .truncate(!append)
-            .open(file)
-            .expect("failed to open stdout redirection file");
-        dup2(file_handle.as_raw_fd(), libc::STDOUT_FILENO)
-            .expect("dup2 failed for stdout");
-    }
-    if let Some((ref file, append)) = redir.stderr {
-        let file_handle = OpenOptions::new()
-            .write(true)
-            .create(true)
-            .append(append)
-            .truncate(!append)
-            .open(file)
-            .expect("failed to open stderr redirection file");
-        dup2(file_handle.as_raw_fd(), libc::STDERR_FILENO)
-            .expect("dup2 failed for stderr");
-    }
-
-    process_command(cmd);
-
-    io::stdout().flush().unwrap();
-    io::stderr().flush().unwrap();
-
-    // Restore original stdout and stderr.
-    dup2(stdout_fd, libc::STDOUT_FILENO)
-        .expect("dup2 restore failed for stdout");
-    dup2(stderr_fd, libc::STDERR_FILENO)
-        .expect("dup2 restore failed for stderr");
-    close(stdout_fd).ok();
-    close(stderr_fd).ok();
+/// Whether the user typed the command now running, outside of any trap.
+pub fn running_typed_command() -> bool {
+    TYPED_COMMAND.load(Ordering::Relaxed) && !traps::in_trap()
 }
 
 // --------------------- Command Processing ---------------------
 
-fn process_command(cmd: &str) {
-    if handle_echo_command(cmd) { return; }
-    if handle_cd_command(cmd) { return; }
-    if handle_type_command(cmd) { return; }
-    if handle_pwd_command(cmd) { return; }
-    if handle_execute_command(cmd) { return; }
-    if handle_exit_command(cmd) { return; }
-    println!("{}: command not found", cmd);
-}
-
-// --------------------- External Command Completion Helpers ---------------------
-
-fn get_external_candidates(prefix: &str) -> Vec<String> {
-    let mut candidates = Vec::new();
-    if let Ok(path_var) = std::env::var("PATH") {
-        for path in path_var.split(':') {
-            if let Ok(entries) = std::fs::read_dir(path) {
-                for entry in entries.flatten() {
-                    if let Ok(metadata) = entry.metadata() {
-                        if metadata.is_file() {
-                            if let Ok(name) = entry.file_name().into_string() {
-                                if name.starts_with(prefix) {
-                                    #[cfg(unix)]
-                                    {
-                                        use std::os::unix::fs::PermissionsExt;
-                                        if metadata.permissions().mode() & 0o111 != 0 {
-                                            candidates.push(name);
-                                        }
-                                    }
-                                    #[cfg(not(unix))]
-                                    {
-                                        candidates.push(name);
-                                    }
-                                }
-                            }
-                        }
-                    }
+/// Exit status of the most recently executed command.
+static LAST_STATUS: AtomicI32 = AtomicI32::new(0);
+
+pub fn last_status() -> i32 {
+    LAST_STATUS.load(Ordering::Relaxed)
+}
+
+pub fn set_last_status(status: i32) {
+    LAST_STATUS.store(status, Ordering::Relaxed);
+}
+
+/// Status passed to `exit`/`logout`, or -1 while the shell should keep running.
+static EXIT_REQUEST: AtomicI32 = AtomicI32::new(-1);
+
+/// Asks the REPL to shut down with `status` once the current command finishes.
+pub fn request_exit(status: i32) {
+    EXIT_REQUEST.store(status, Ordering::Relaxed);
+}
+
+/// Withdraws a pending exit, so that the EXIT trap can run to completion.
+pub fn cancel_exit() {
+    EXIT_REQUEST.store(-1, Ordering::Relaxed);
+}
+
+pub fn exit_requested() -> Option<i32> {
+    let status = EXIT_REQUEST.load(Ordering::Relaxed);
+    (status >= 0).then_some(status)
+}
+
+/// Whether the shell was started as a login shell (`-l`, `--login`, or a `-` argv[0]).
+static LOGIN_SHELL: AtomicBool = AtomicBool::new(false);
+
+pub fn is_login_shell() -> bool {
+    LOGIN_SHELL.load(Ordering::Relaxed)
+}
+
+/// Whether commands are read from a terminal.
+static INTERACTIVE: AtomicBool = AtomicBool::new(false);
+
+pub fn is_interactive() -> bool {
+    INTERACTIVE.load(Ordering::Relaxed)
+}
+
+/// Parses and runs one line of input, which may hold several commands.
+pub fn execute_line(line: &str) {
+    let lexed = lexer::tokenize(&lexer::join_continuations(line));
+    match parser::parse(&lexed.tokens) {
+        // Like bash, an interactive shell ignores `set -n`, which would leave no way to undo it.
+        Ok(_) if set::is_enabled("noexec") && !is_interactive() => {}
+        Ok(list) => run_list(&list),
+        Err(error) => {
+            eprintln!("{}", error);
+            set_last_status(2);
+        }
+    }
+}
+
+fn run_list(list: &List) {
+    for (i, (connector, pipeline)) in list.iter().enumerate() {
+        let skip = match connector {
+            Connector::Seq => false,
+            Connector::And => last_status() != 0,
+            Connector::Or => last_status() == 0,
+        };
+        if skip {
+            continue;
+        }
+        let status = run_pipeline(pipeline);
+        set_last_status(status);
+        // A failed expansion abandons the rest of the line, as does `exit`.
+        if take_expansion_error() || exit_requested().is_some() {
+            return;
+        }
+        // Signals that arrived while the command ran are handled between commands.
+        traps::run_pending();
+        if exit_requested().is_some() {
+            return;
+        }
+        // `set -e` and the ERR trap spare a failure whose status is being tested:
+        // one inverted with `!`, or followed by `&&` or `||`.
+        let tested = pipeline.negated || list.get(i + 1).is_some_and(|(next, _)| *next != Connector::Seq);
+        if status != 0 && !tested {
+            traps::run_err();
+            if exit_requested().is_some() {
+                return;
+            }
+            if set::is_enabled("errexit") {
+                request_exit(status);
+                return;
+            }
+        }
+    }
+}
+
+/// Prints a command about to run for `set -x`, after the expanded `PS4`.
+fn trace(words: &[String]) {
+    if set::is_enabled("xtrace") {
+        eprintln!("{}{}", prompt_string("PS4"), words.join(" "));
+    }
+}
+
+/// Expands a prompt variable such as `PS2` or `PS4`.
+fn prompt_string(name: &str) -> String {
+    // Expanded as if double-quoted, so that its spacing survives.
+    let value = vars::get(name).unwrap_or_default().replace('\\', "\\\\").replace('"', "\\\"");
+    unquoted(&expand_word(&format!("\"{}\"", value)))
+}
+
+fn run_pipeline(pipeline: &Pipeline) -> i32 {
+    traps::run_debug();
+    let status = match &pipeline.command {
+        Command::Simple(simple) => run_simple(simple),
+        Command::Conditional(words) => {
+            if set::is_enabled("xtrace") {
+                // Command substitutions and arithmetic are left as written, so that
+                // tracing cannot run them twice.
+                let shown = words.iter().map(|word| if word.contains("$(") { word.clone() } else { single_quote(&unquoted(&expand_word(word))) });
+                let shown: Vec<String> = ["[[".to_string()].into_iter().chain(shown).chain(["]]".to_string()]).collect();
+                trace(&shown);
+            }
+            eval_conditional(words)
+        }
+        Command::Arithmetic(expression) => {
+            trace(&[format!("(( {} ))", expression)]);
+            match evaluate_arithmetic(expression) {
+                Some(value) => (value == 0) as i32,
+                None => {
+                    take_expansion_error();
+                    1
                 }
             }
         }
+    };
+    if pipeline.negated {
+        (status == 0) as i32
+    } else {
+        status
+    }
+}
+
+fn run_simple(simple: &SimpleCommand) -> i32 {
+    // Compound array assignments such as `arr=(a b)` are made before anything is expanded.
+    let mut words = &simple.words[..];
+    let mut status = 0;
+    while let Some((name, append, inner)) = words.first().and_then(|word| compound_assignment(word)) {
+        trace(&words[..1]);
+        if !assign_compound(name, append, inner) {
+            status = 1;
+        }
+        words = &words[1..];
+    }
+    if words.is_empty() && simple.redirects.is_empty() {
+        return status;
+    }
+    // Brace expansion comes first, and leaves the assignments before the command alone.
+    let assignments = words.iter().take_while(|word| vars::parse_assignment(word).is_some()).count();
+    let words: Vec<String> = words[..assignments]
+        .iter()
+        .cloned()
+        .chain(words[assignments..].iter().flat_map(|word| braces::expand(word)))
+        .collect();
+    // `declare` receives its compound assignments unexpanded, to expand them itself.
+    let declaration = words.first().is_some_and(|word| word == "declare" || word == "typeset");
+    let args: Vec<String> = words
+        .iter()
+        .enumerate()
+        .flat_map(|(i, word)| match compound_assignment(word) {
+            Some(_) if declaration => vec![word.clone()],
+            // Assignment values are not split into fields.
+            _ if i < assignments || (declaration && vars::parse_assignment(word).is_some()) => {
+                vec![expand_assignment(word)]
+            }
+            _ => parse_parameters(word),
+        })
+        .collect();
+    if expansion_failed() {
+        return 1;
+    }
+    if set::is_enabled("xtrace") {
+        // Each assignment gets a line of its own, ahead of the command.
+        for assignment in &args[..assignments] {
+            let value = vars::parse_assignment(assignment).map_or("", |assignment| assignment.value);
+            let target = &assignment[..assignment.len() - value.len()];
+            let value = if value.is_empty() { String::new() } else { single_quote(value) };
+            trace(&[format!("{}{}", target, value)]);
+        }
+        let command: Vec<String> = args[assignments..].iter().map(|arg| single_quote(arg)).collect();
+        if !command.is_empty() {
+            trace(&command);
+        }
+    }
+    if simple.redirects.is_empty() {
+        run_argv(&args)
+    } else {
+        run_command_with_redirections(&args, &simple.redirects)
+    }
+}
+
+/// Runs an already-split command line, preferring builtins over PATH executables.
+pub fn run_argv(args: &[String]) -> i32 {
+    let split = args.iter().take_while(|arg| vars::parse_assignment(arg).is_some()).count();
+    let (assignments, args) = args.split_at(split);
+    let assignments = assignments.iter().filter_map(|arg| vars::parse_assignment(arg));
+    if args.is_empty() {
+        let mut status = 0;
+        for assignment in assignments {
+            if !assign(&assignment) {
+                status = 1;
+            }
+        }
+        return status;
+    }
+    // Assignments before a command only last for that command, and are exported to it.
+    let saved: Vec<_> = assignments
+        .map(|assignment| {
+            let name = assignment.name;
+            let saved = vars::save(name);
+            let mut value = String::new();
+            if assignment.append {
+                value = vars::get(name).unwrap_or_default();
+            }
+            value.push_str(assignment.value);
+            vars::set_exported(name, &value);
+            saved
+        })
+        .collect();
+    let status = run_simple_command(args);
+    for saved in saved.into_iter().rev() {
+        vars::restore(saved);
+    }
+    status
+}
+
+fn run_simple_command(args: &[String]) -> i32 {
+    let Some((name, rest)) = args.split_first() else { return 0 };
+    if let Some(builtin) = commands::lookup(name) {
+        return builtin.run(rest, &mut Io::std());
     }
-    candidates
+    if rest.is_empty() && shopt::is_enabled("autocd") && Path::new(name).is_dir()
+        && find_executable(name).is_none()
+    {
+        return run_argv(&["cd".to_string(), name.clone()]);
+    }
+    run_external(name, rest)
 }
 
+// --------------------- Completion Helpers ---------------------
+
+/// Returns the longest common prefix of a list of strings.
 fn longest_common_prefix(strings: &[String]) -> String {
     if strings.is_empty() { return "".to_string(); }
     let mut prefix = strings[0].clone();
@@ -171,11 +406,14 @@ fn longest_common_prefix(strings: &[String]) -> String {
 
 use rustyline::completion::{Completer, Candidate};
 use rustyline::error::ReadlineError;
-use rustyline::{Editor, Context, Helper};
+use rustyline::{Cmd, ConditionalEventHandler, Context, Editor, Event, EventContext, EventHandler, Helper};
+use rustyline::{KeyCode, KeyEvent, Modifiers, RepeatCount};
 use rustyline::hint::Hinter;
 use rustyline::highlight::Highlighter;
-use rustyline::validate::Validator;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::config::Configurer;
 use rustyline::history::DefaultHistory;
+use std::borrow::Cow;
 use std::cell::RefCell;
 
 #[derive(Debug)]
@@ -186,26 +424,26 @@ impl Candidate for MyCandidate {
 }
 
 struct MyHelper {
-    // Store state for autocompletion.
+    // Store the last input and completion count for repeated TAB presses.
     last_input: RefCell<Option<String>>,
     completion_count: RefCell<usize>,
+    // The prompt being shown, to redraw under a list of candidates.
+    prompt: RefCell<String>,
+    // Whether the last input the validator saw was an unfinished command.
+    unfinished: RefCell<bool>,
 }
 impl MyHelper {
     fn new() -> Self {
-        MyHelper { last_input: RefCell::new(None), completion_count: RefCell::new(0) }
+        MyHelper { last_input: RefCell::new(None), completion_count: RefCell::new(0), prompt: RefCell::new(String::new()), unfinished: RefCell::new(false) }
     }
 }
+
 impl Completer for MyHelper {
     type Candidate = MyCandidate;
-
-    fn complete(
-        &self,
-        line: &str,
-        _pos: usize,
-        _ctx: &Context<'_>
-    ) -> rustyline::Result<(usize, Vec<MyCandidate>)> {
-
-        // Reset state if the line has changed.
+    fn complete(&self, line: &str, _pos: usize, _ctx: &Context<'_>)
+        -> rustyline::Result<(usize, Vec<MyCandidate>)>
+    {
+        // Reset state if input changed.
         {
             let mut last = self.last_input.borrow_mut();
             if last.as_deref() != Some(line) {
@@ -216,407 +454,421 @@ impl Completer for MyHelper {
         *self.completion_count.borrow_mut() += 1;
         let count = *self.completion_count.borrow();
 
-        // Built-in commands:
-        let builtin_candidates: Vec<String> = ["echo", "exit"]
-            .iter()
-            .filter(|&&cmd| cmd.starts_with(line) && cmd != line)
-            .map(|&s| s.to_string())
-            .collect();
-
-        // External commands:
-        let mut external_candidates = get_external_candidates(line);
-        external_candidates.sort();
-        external_candidates.dedup();
-
-        // Combine them:
-        let mut all_candidates = builtin_candidates;
-        all_candidates.extend(external_candidates);
-        all_candidates.sort();
-        all_candidates.dedup();
-
+        if let Some((idx, c)) = line.char_indices().rev().find(|(_, c)| c.is_whitespace()) {
+            let start = idx + c.len_utf8();
+            if let Some(dir) = expand_stack_tilde(&line[start..]) {
+                // `~N` completes to the directory stack entry it names.
+                return Ok((start, vec![MyCandidate(format!("{}/", dir.display()))]));
+            }
+        }
+        let Some((start, all_candidates)) = completion_candidates(line) else {
+            return Ok((0, Vec::new()));
+        };
+        let word = &line[start..];
         if all_candidates.is_empty() {
             return Ok((0, Vec::new()));
         }
 
-        // If there's exactly one candidate, return it with a trailing space:
+        // Compute the longest common prefix.
         if all_candidates.len() == 1 {
             let candidate = &all_candidates[0];
-            // Always append a space to match the test's expectation.
-            return Ok((0, vec![MyCandidate(format!("{} ", candidate))]));
+            // Always append a normal space so the test sees a trailing space.
+            return Ok((start, vec![MyCandidate(format!("{} ", candidate))]));
         }
-
-        // Otherwise, see if there's a usable longest common prefix:
         let lcp = longest_common_prefix(&all_candidates);
-        if lcp.len() > line.len() {
-            // Provide the longest common prefix as the single completion
-            return Ok((0, vec![MyCandidate(lcp)]));
+        if lcp.len() > word.len() {
+            // Multiple candidates: complete to the longest common prefix with no trailing space.
+            return Ok((start, vec![MyCandidate(lcp)]));
         }
-
-        // If no progress can be made, handle repeated TAB presses:
+        // Multiple matches with no further extension.
         if count == 1 {
-            // On first TAB with multiple matches, beep:
-            return Ok((0, Vec::new()));
+            // First TAB: ring bell (return no candidates).
+            Ok((0, Vec::new()))
         } else {
-            println!(); // Blank line
+            // Second (or later) TAB: print all candidates, then redraw the prompt and the partial line.
+            println!();
             println!("{}", all_candidates.join("  "));
-            
-            // Re‐show prompt and the partial line
-            print!("$ {}", line);
+            print!("{}{}", self.prompt.borrow(), line);
             std::io::stdout().flush().unwrap();
-        
             *self.completion_count.borrow_mut() = 0;
-            return Ok((0, Vec::new()));
+            Ok((0, Vec::new()))
         }
     }
 }
 
+/// The start of the word being completed at the end of `line` and its sorted
+/// candidates, or `None` when nothing knows how to complete it.
+fn completion_candidates(line: &str) -> Option<(usize, Vec<String>)> {
+    let (start, mut candidates) = match line.char_indices().rev().find(|(_, c)| c.is_whitespace()) {
+        // Past the first word: hand off to the per-command providers.
+        Some((idx, c)) => {
+            let start = idx + c.len_utf8();
+            let previous = parse_parameters(&line[..start]);
+            let (command, args) = previous.split_first()?;
+            (start, complete_argument(command, args, &line[start..])?)
+        }
+        None => {
+            // Gather builtin candidates.
+            let mut candidates: Vec<String> = builtins()
+                .map(|builtin| builtin.name())
+                .filter(|&cmd| cmd.starts_with(line) && cmd != line)
+                .map(|s| s.to_string())
+                .collect();
+            // Gather external candidates.
+            candidates.append(&mut hash::candidates(line));
+            (0, candidates)
+        }
+    };
+    candidates.sort();
+    candidates.dedup();
+    Some((start, candidates))
+}
+
 impl Hinter for MyHelper {
     type Hint = String;
-    fn hint(&self, _line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> { None }
+    /// Suggests the rest of the line from history, like fish: the latest entry
+    /// run in this directory, then the latest anywhere, and failing both the
+    /// rest of the word when it has exactly one completion.
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> Option<String> {
+        if pos < line.len() {
+            return None;
+        }
+        if let Some(rest) = history::suggest(line, &logical_cwd().to_string_lossy()) {
+            return Some(rest);
+        }
+        // Completion expands the earlier words; don't run `$(( ))` on every keystroke.
+        if line.is_empty() || line.contains(['$', '`']) {
+            return None;
+        }
+        let (start, candidates) = completion_candidates(line)?;
+        match &candidates[..] {
+            [only] => only.strip_prefix(&line[start..]).filter(|rest| !rest.is_empty()).map(str::to_string),
+            _ => None,
+        }
+    }
+}
+
+/// Replaces reverse-i-search with a fuzzy finder over history. The chosen line
+/// is put in the edit buffer, with the cursor at its end, to be run with Enter.
+struct FuzzySearch;
+impl ConditionalEventHandler for FuzzySearch {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        let line = fuzzy::search(ctx.line()).unwrap_or_else(|| ctx.line().to_string());
+        // A handler can replace the buffer but not place the cursor after the new
+        // text, so end this edit and let the REPL start a new one from the line.
+        fuzzy::set_chosen(line);
+        Some(Cmd::Interrupt)
+    }
+}
+
+/// Runs a `bind -x` command. Like a fuzzy search, it ends the edit so the REPL
+/// can run the command and start a new edit from the line it leaves behind.
+struct RunCommand(String);
+impl ConditionalEventHandler for RunCommand {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        keybindings::request_command(self.0.clone(), ctx.line().to_string(), ctx.pos());
+        Some(Cmd::Interrupt)
+    }
+}
+
+/// Ends the edit when a signal feeds the editor its wake-up key, so that the REPL
+/// can run the signal's trap and then carry on with the same line.
+struct WakeForTraps;
+impl ConditionalEventHandler for WakeForTraps {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        if !traps::woken() {
+            return None;
+        }
+        traps::set_interrupted_line(ctx.line().to_string(), ctx.pos());
+        Some(Cmd::Interrupt)
+    }
+}
+
+/// Accepts the shown hint on End, as Right already does; End moves as usual otherwise.
+struct AcceptHint;
+impl ConditionalEventHandler for AcceptHint {
+    fn handle(&self, _evt: &Event, _n: RepeatCount, _positive: bool, ctx: &EventContext) -> Option<Cmd> {
+        (ctx.has_hint() && ctx.pos() == ctx.line().len()).then_some(Cmd::CompleteHint)
+    }
+}
+impl Highlighter for MyHelper {
+    fn highlight<'l>(&self, line: &'l str, _pos: usize) -> Cow<'l, str> {
+        Cow::Owned(highlight::highlight(line))
+    }
+    fn highlight_hint<'h>(&self, hint: &'h str) -> Cow<'h, str> {
+        Cow::Owned(highlight::highlight_hint(hint))
+    }
+    fn highlight_char(&self, _line: &str, _pos: usize) -> bool {
+        // Any keystroke can change how the rest of the line lexes.
+        true
+    }
+}
+/// Keeps reading while the command is unfinished. Input that is not a terminal
+/// is joined here into one block; at the prompt Enter always hands the line
+/// over, and the REPL reads on after `$PS2`.
+impl Validator for MyHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        let lexed = lexer::tokenize(&lexer::join_continuations(ctx.input()));
+        let unfinished = parser::is_incomplete(&lexed);
+        self.unfinished.replace(unfinished);
+        if unfinished {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
 }
-impl Highlighter for MyHelper {}
-impl Validator for MyHelper {}
 impl Helper for MyHelper {}
 
 // --------------------- REPL Loop using Rustyline ---------------------
 
+/// Where history is persisted: `$HISTFILE`, falling back to `~/.shell_history`.
+fn history_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var("HISTFILE") {
+        return Some(PathBuf::from(path));
+    }
+    env::var("HOME").ok().map(|home| PathBuf::from(home).join(".shell_history"))
+}
+
+/// Where the structured history sits: next to the history file, with `.log` appended.
+fn history_log_path(history: &Path) -> PathBuf {
+    let mut name = history.as_os_str().to_os_string();
+    name.push(".log");
+    PathBuf::from(name)
+}
+
+/// Startup commands for interactive shells, such as `bind` and `set -o vi`.
+fn rc_path() -> Option<PathBuf> {
+    env::var("HOME").ok().map(|home| PathBuf::from(home).join(".shellrc"))
+}
+
+/// Runs each command in the rc file, reading on while a command is unfinished.
+fn load_rc(path: &Path) {
+    let Ok(contents) = std::fs::read_to_string(path) else { return };
+    let mut pending = String::new();
+    for line in contents.lines() {
+        echo_input(line);
+        pending.push_str(line);
+        pending.push('\n');
+        if parser::is_incomplete(&lexer::tokenize(&lexer::join_continuations(&pending))) {
+            continue;
+        }
+        execute_line(pending.trim_end_matches('\n'));
+        pending.clear();
+    }
+    if !pending.trim().is_empty() {
+        execute_line(pending.trim_end_matches('\n'));
+    }
+}
+
+/// Echoes a line of input to stderr as it is read, for `set -v`.
+fn echo_input(line: &str) {
+    if set::is_enabled("verbose") {
+        eprintln!("{}", line);
+    }
+}
+
+/// Hands the editor any modes and bindings set by `set -o` or `bind` since it last prompted.
+fn apply_editor_changes(rl: &mut Editor<MyHelper, DefaultHistory>) {
+    for change in keybindings::take_changes() {
+        match change {
+            Change::Mode(mode) => rl.set_edit_mode(mode),
+            Change::Bind(keys, action) => {
+                let handler = match action {
+                    Action::Function(name) if name == keybindings::FUZZY_SEARCH => {
+                        EventHandler::Conditional(Box::new(FuzzySearch))
+                    }
+                    Action::Function(name) => match keybindings::editing_function(&name) {
+                        Some(cmd) => EventHandler::Simple(cmd),
+                        None => continue,
+                    },
+                    Action::Command(command) => EventHandler::Conditional(Box::new(RunCommand(command))),
+                };
+                rl.bind_sequence(Event::KeySeq(keys), handler);
+            }
+            Change::Unbind(keys) => {
+                rl.unbind_sequence(Event::KeySeq(keys));
+            }
+        }
+    }
+}
+
+/// Runs a `bind -x` command with the edited line in `READLINE_LINE` and the
+/// cursor's character offset in `READLINE_POINT`, returning the line and
+/// cursor byte offset the command leaves.
+fn run_bound_command(command: &str, line: &str, pos: usize) -> (String, usize) {
+    vars::set("READLINE_LINE", line);
+    vars::set("READLINE_POINT", &line[..pos].chars().count().to_string());
+    execute_line(command);
+    io::stdout().flush().ok();
+    let line = vars::get("READLINE_LINE").unwrap_or_default();
+    let point = vars::get("READLINE_POINT").and_then(|point| point.parse().ok()).unwrap_or(0);
+    let pos = line.char_indices().nth(point).map_or(line.len(), |(i, _)| i);
+    (line, pos)
+}
+
+/// Saves history and terminates the process.
+fn shutdown(rl: &mut Editor<MyHelper, DefaultHistory>, status: i32) -> ! {
+    let status = traps::run_exit(status);
+    if let Some(path) = history_path() {
+        let _ = if shopt::is_enabled("histappend") { rl.append_history(&path) } else { rl.save_history(&path) };
+    }
+    history::trim_log();
+    io::stdout().flush().ok();
+    std::process::exit(status);
+}
+
 pub fn start_shell() {
-    let mut rl = Editor::<MyHelper, DefaultHistory>::new().unwrap();
+    let mut args = env::args();
+    let login = args.next().is_some_and(|arg0| arg0.starts_with('-'))
+        || args.any(|arg| arg == "-l" || arg == "--login");
+    LOGIN_SHELL.store(login, Ordering::Relaxed);
+    // Start from a trustworthy PWD so `cd -L` and `pwd -L` have something to build on.
+    env::set_var("PWD", logical_cwd());
+    // Like other shells, ignore any inherited IFS and start from the default separators.
+    env::remove_var("IFS");
+    vars::set("IFS", " \t\n");
+    for (name, default) in [("PS2", "> "), ("PS4", "+ ")] {
+        if vars::get(name).is_none() {
+            vars::set(name, default);
+        }
+    }
+    INTERACTIVE.store(isatty(libc::STDIN_FILENO).unwrap_or(false), Ordering::Relaxed);
+
+    use rustyline::Config;
+    let config = Config::builder()
+        .build();
+    let mut rl = Editor::<MyHelper, DefaultHistory>::with_config(config).unwrap();
     rl.set_helper(Some(MyHelper::new()));
+    // Accept even an unfinished line, so that its continuation is read after `$PS2`.
+    rl.bind_sequence(KeyEvent(KeyCode::Enter, Modifiers::NONE), EventHandler::Simple(Cmd::AcceptLine));
+    rl.bind_sequence(KeyEvent::ctrl('J'), EventHandler::Simple(Cmd::AcceptLine));
+    rl.bind_sequence(KeyEvent::ctrl('R'), EventHandler::Conditional(Box::new(FuzzySearch)));
+    rl.bind_sequence(KeyEvent(KeyCode::End, Modifiers::NONE), EventHandler::Conditional(Box::new(AcceptHint)));
+    rl.bind_sequence(KeyEvent::new(traps::WAKE_KEY, Modifiers::NONE), EventHandler::Conditional(Box::new(WakeForTraps)));
+    if let Some(path) = history_path() {
+        let _ = rl.load_history(&path);
+    }
+    // Without a structured log yet, start from the plain history file's lines.
+    if !history_path().is_some_and(|path| history::open(&history_log_path(&path))) {
+        for line in rl.history().iter() {
+            history::record(history::Entry { line: line.clone(), ..Default::default() });
+        }
+    }
+    if is_interactive() {
+        if let Some(path) = rc_path() {
+            load_rc(&path);
+        }
+        if let Some(status) = exit_requested() {
+            shutdown(&mut rl, status);
+        }
+    }
+    // A line to start the next edit from, with the cursor's byte offset.
+    let mut initial: Option<(String, usize)> = None;
+    // The lines read so far of a command that is not finished yet.
+    let mut pending = String::new();
     loop {
-        let readline = rl.readline("$ ");
+        apply_editor_changes(&mut rl);
+        let prompt = if pending.is_empty() { "$ ".to_string() } else { prompt_string("PS2") };
+        if let Some(helper) = rl.helper_mut() {
+            helper.prompt.replace(prompt.clone());
+            helper.unfinished.replace(false);
+        }
+        if is_interactive() {
+            traps::enter_prompt();
+        }
+        let readline = match initial.take() {
+            Some((line, pos)) => rl.readline_with_initial(&prompt, line.split_at(pos)),
+            None => rl.readline(&prompt),
+        };
+        traps::leave_prompt();
         match readline {
             Ok(line) => {
-                rl.add_history_entry(line.as_str());
-                let trimmed = line.trim_end_matches('\n').replace("\u{00A0}", " ");
-                if trimmed.is_empty() { continue; }
-                if trimmed.contains('>') {
-                    let (cmd_part, redir_spec) = parse_command_and_redirections(&trimmed);
-                    run_command_with_redirections(&cmd_part, redir_spec);
-                } else {
-                    process_command(&trimmed);
-                }
-            },
-            Err(ReadlineError::Interrupted) => {
-                println!("CTRL-C");
-                continue;
-            },
-            Err(ReadlineError::Eof) => {
-                println!("CTRL-D");
-                break;
-            },
-            Err(err) => {
-                println!("Error: {:?}", err);
-                break;use nix::libc;
-                use std::io::{self, Write};
-                
-                // Import command handlers.
-                use crate::commands::echo::handle_echo_command;
-                use crate::commands::cd::handle_cd_command;
-                use crate::commands::type_cmd::handle_type_command;
-                use crate::commands::pwd::handle_pwd_command;
-                use crate::commands::execute::handle_execute_command;
-                use crate::commands::exit::handle_exit_command;
-                
-                // --------------------- Redirection Support ---------------------
-                
-                #[derive(Debug)]
-                struct RedirectionSpec {
-                    stdout: Option<(String, bool)>, // (filename, append) for stdout
-                    stderr: Option<(String, bool)>, // (filename, append) for stderr
+                // A signal may have come in just as the line was entered.
+                traps::run_pending();
+                if let Some(status) = exit_requested() {
+                    shutdown(&mut rl, status);
                 }
-                
-                /// Splits an input line into the command portion and a redirection specification.
-                /// Assumes redirection tokens (>, >>, 2>, 2>>) are separated by whitespace.
-                fn parse_command_and_redirections(input: &str) -> (String, RedirectionSpec) {
-                    let mut redir = RedirectionSpec {
-                        stdout: None,
-                        stderr: None,
-                    };
-                    let tokens: Vec<&str> = input.split_whitespace().collect();
-                    let mut cmd_tokens = Vec::new();
-                    let mut i = 0;
-                    while i < tokens.len() {
-                        match tokens[i] {
-                            "2>>" => {
-                                if i + 1 < tokens.len() {
-                                    redir.stderr = Some((tokens[i + 1].to_string(), true));
-                                    i += 2;
-                                } else { break; }
-                            }
-                            "2>" => {
-                                if i + 1 < tokens.len() {
-                                    redir.stderr = Some((tokens[i + 1].to_string(), false));
-                                    i += 2;
-                                } else { break; }
-                            }
-                            "1>>" | ">>" => {
-                                if i + 1 < tokens.len() {
-                                    redir.stdout = Some((tokens[i + 1].to_string(), true));
-                                    i += 2;
-                                } else { break; }
-                            }
-                            "1>" | ">" => {
-                                if i + 1 < tokens.len() {
-                                    redir.stdout = Some((tokens[i + 1].to_string(), false));
-                                    i += 2;
-                                } else { break; }
-                            }
-                            token => {
-                                cmd_tokens.push(token);
-                                i += 1;
-                            }
-                        }
-                    }
-                    (cmd_tokens.join(" "), redir)
+                echo_input(&line);
+                if !pending.is_empty() {
+                    pending.push('\n');
                 }
-                
-                /// Executes a command with redirections applied.
-                fn run_command_with_redirections(cmd: &str, redir: RedirectionSpec) {
-                    use std::fs::OpenOptions;
-                    use std::os::unix::io::AsRawFd;
-                    use nix::unistd::{dup, dup2, close};
-                
-                    // Save original stdout and stderr.
-                    let stdout_fd = dup(libc::STDOUT_FILENO).expect("dup failed for stdout");
-                    let stderr_fd = dup(libc::STDERR_FILENO).expect("dup failed for stderr");
-                
-                    if let Some((ref file, append)) = redir.stdout {
-                        let file_handle = OpenOptions::new()
-                            .write(true)
-                            .create(true)
-                            .append(append)
-                            .truncate(!append)
-                            .open(file)
-                            .expect("failed to open stdout redirection file");
-                        dup2(file_handle.as_raw_fd(), libc::STDOUT_FILENO)
-                            .expect("dup2 failed for stdout");
-                    }
-                    if let Some((ref file, append)) = redir.stderr {
-                        let file_handle = OpenOptions::new()
-                            .write(true)
-                            .create(true)
-                            .append(append)
-                            .truncate(!append)
-                            .open(file)
-                            .expect("failed to open stderr redirection file");
-                        dup2(file_handle.as_raw_fd(), libc::STDERR_FILENO)
-                            .expect("dup2 failed for stderr");
-                    }
-                
-                    process_command(cmd);
-                
-                    io::stdout().flush().unwrap();
-                    io::stderr().flush().unwrap();
-                
-                    // Restore original stdout and stderr.
-                    dup2(stdout_fd, libc::STDOUT_FILENO)
-                        .expect("dup2 restore failed for stdout");
-                    dup2(stderr_fd, libc::STDERR_FILENO)
-                        .expect("dup2 restore failed for stderr");
-                    close(stdout_fd).ok();
-                    close(stderr_fd).ok();
+                pending.push_str(&line);
+                if parser::is_incomplete(&lexer::tokenize(&lexer::join_continuations(&pending))) {
+                    continue;
                 }
-                
-                // --------------------- Command Processing ---------------------
-                
-                fn process_command(cmd: &str) {
-                    if handle_echo_command(cmd) { return; }
-                    if handle_cd_command(cmd) { return; }
-                    if handle_type_command(cmd) { return; }
-                    if handle_pwd_command(cmd) { return; }
-                    if handle_execute_command(cmd) { return; }
-                    if handle_exit_command(cmd) { return; }
-                    println!("{}: command not found", cmd);
+                // The whole command is run and remembered as one entry.
+                let line = std::mem::take(&mut pending);
+                let _ = rl.add_history_entry(line.as_str());
+                let trimmed = line.trim_end_matches('\n').replace("\u{00A0}", " ");
+
+                if trimmed.is_empty() {
+                    continue;
                 }
-                
-                // --------------------- External Completion Helpers ---------------------
-                
-                /// Returns all external executable candidates in PATH matching the given prefix.
-                fn get_external_candidates(prefix: &str) -> Vec<String> {
-                    let mut candidates: Vec<String> = Vec::new();
-                    if let Ok(path_var) = std::env::var("PATH") {
-                        for dir in path_var.split(':') {
-                            if let Ok(entries) = std::fs::read_dir(dir) {
-                                for entry in entries.flatten() {
-                                    if let Ok(metadata) = entry.metadata() {
-                                        if metadata.is_file() {
-                                            if let Ok(name) = entry.file_name().into_string() {
-                                                if name.starts_with(prefix) {
-                                                    #[cfg(unix)]
-                                                    {
-                                                        use std::os::unix::fs::PermissionsExt;
-                                                        if metadata.permissions().mode() & 0o111 != 0 {
-                                                            candidates.push(name);
-                                                        }
-                                                    }
-                                                    #[cfg(not(unix))]
-                                                    {
-                                                        candidates.push(name);
-                                                    }
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    candidates
+                history::record(history::Entry {
+                    line,
+                    cwd: Some(logical_cwd().to_string_lossy().into_owned()),
+                    start: Some(history::now()),
+                    session: Some(history::session_id().to_string()),
+                    ..Default::default()
+                });
+                let started = Instant::now();
+                TYPED_COMMAND.store(is_interactive(), Ordering::Relaxed);
+                execute_line(&trimmed);
+                TYPED_COMMAND.store(false, Ordering::Relaxed);
+                history::finish(started.elapsed().as_millis() as u64, last_status());
+                if let Some(status) = exit_requested() {
+                    shutdown(&mut rl, status);
                 }
-                
-                /// Returns the longest common prefix of a list of strings.
-                fn longest_common_prefix(strings: &[String]) -> String {
-                    if strings.is_empty() { return "".to_string(); }
-                    let mut prefix = strings[0].clone();
-                    for s in strings.iter().skip(1) {
-                        while !s.starts_with(&prefix) {
-                            prefix.pop();
-                            if prefix.is_empty() { break; }
-                        }
+            },
+            Err(ReadlineError::Interrupted) => {
+                if let Some((line, pos)) = traps::take_interrupted_line() {
+                    // A signal ended the edit: run its trap in place of the prompt
+                    // line, then pick the edit up again.
+                    print!("\x1b[A\r\x1b[K");
+                    io::stdout().flush().ok();
+                    traps::run_pending();
+                    io::stdout().flush().ok();
+                    if let Some(status) = exit_requested() {
+                        shutdown(&mut rl, status);
                     }
-                    prefix
+                    initial = Some((line, pos));
+                    continue;
                 }
-                
-                // --------------------- Rustyline Autocompletion ---------------------
-                
-                use rustyline::completion::{Completer, Candidate};
-                use rustyline::error::ReadlineError;
-                use rustyline::{Editor, Context, Helper};
-                use rustyline::hint::Hinter;
-                use rustyline::highlight::Highlighter;
-                use rustyline::validate::Validator;
-                use rustyline::history::DefaultHistory;
-                use std::cell::RefCell;
-                
-                #[derive(Debug)]
-                struct MyCandidate(String);
-                impl Candidate for MyCandidate {
-                    fn display(&self) -> &str { &self.0 }
-                    fn replacement(&self) -> &str { &self.0 }
+                if let Some(line) = fuzzy::take_chosen() {
+                    // Go back up to redraw the prompt over the interrupted line.
+                    print!("\x1b[A\r\x1b[K");
+                    io::stdout().flush().ok();
+                    initial = Some((line.clone(), line.len()));
+                    continue;
                 }
-                
-                struct MyHelper {
-                    // Store the last input and completion count for repeated TAB presses.
-                    last_input: RefCell<Option<String>>,
-                    completion_count: RefCell<usize>,
+                if let Some((command, line, pos)) = keybindings::take_command_request() {
+                    initial = Some(run_bound_command(&command, &line, pos));
+                    continue;
                 }
-                impl MyHelper {
-                    fn new() -> Self {
-                        MyHelper { last_input: RefCell::new(None), completion_count: RefCell::new(0) }
-                    }
-                }
-                
-                impl Completer for MyHelper {
-                    type Candidate = MyCandidate;
-                    fn complete(&self, line: &str, _pos: usize, _ctx: &Context<'_>) 
-                        -> rustyline::Result<(usize, Vec<MyCandidate>)> 
-                    {
-                        // If the line is not a single token, do not complete.
-                        if line.contains(' ') {
-                            return Ok((0, Vec::new()));
-                        }
-                        // Reset state if input changed.
-                        {
-                            let mut last = self.last_input.borrow_mut();
-                            if last.as_deref() != Some(line) {
-                                *last = Some(line.to_string());
-                                *self.completion_count.borrow_mut() = 0;
-                            }
-                        }
-                        *self.completion_count.borrow_mut() += 1;
-                        let count = *self.completion_count.borrow();
-                
-                        // Gather builtin candidates.
-                        let mut all_candidates: Vec<String> = ["echo", "exit"]
-                            .iter()
-                            .filter(|&&cmd| cmd.starts_with(line) && cmd != line)
-                            .map(|&s| s.to_string())
-                            .collect();
-                        // Gather external candidates.
-                        let mut external_candidates = get_external_candidates(line);
-                        all_candidates.append(&mut external_candidates);
-                        all_candidates.sort();
-                        all_candidates.dedup();
-                
-                        if all_candidates.is_empty() {
-                            return Ok((0, Vec::new()));
-                        }
-                
-                        // Compute the longest common prefix.
-                        if all_candidates.len() == 1 {
-                            let candidate = &all_candidates[0];
-                            // Always append a normal space so the test sees a trailing space.
-                            return Ok((0, vec![MyCandidate(format!("{} ", candidate))]));
-                        }
-                        let lcp = longest_common_prefix(&all_candidates);
-                        if lcp.len() > line.len() {
-                            // Multiple candidates: complete to the longest common prefix with no trailing space.
-                            return Ok((0, vec![MyCandidate(lcp)]));
-                        }
-                        // Multiple matches with no further extension.
-                        if count == 1 {
-                            // First TAB: ring bell (return no candidates).
-                            return Ok((0, Vec::new()));
-                        } else {
-                            // Second (or later) TAB: print all candidates, then return the current line.
-                            println!();
-                            println!("{}", all_candidates.join("  "));
-                            *self.completion_count.borrow_mut() = 0;
-                            return Ok((0, vec![MyCandidate(line.to_string())]));
-                        }
-                    }
+                // Ctrl-C abandons an unfinished command. It reaches us as a key, not as SIGINT.
+                pending.clear();
+                if !traps::interrupt() {
+                    println!("CTRL-C");
                 }
-                impl Hinter for MyHelper {
-                    type Hint = String;
-                    fn hint(&self, _line: &str, _pos: usize, _ctx: &Context<'_>) -> Option<String> { None }
+                if let Some(status) = exit_requested() {
+                    shutdown(&mut rl, status);
                 }
-                impl Highlighter for MyHelper {}
-                impl Validator for MyHelper {}
-                impl Helper for MyHelper {}
-                
-                // --------------------- REPL Loop using Rustyline ---------------------
-                
-                pub fn start_shell() {
-                    use rustyline::Config;
-                    let config = Config::builder()
-                        .build();
-                    let mut rl = Editor::<MyHelper, DefaultHistory>::with_config(config).unwrap();
-                    rl.set_helper(Some(MyHelper::new()));
-                    loop {
-                        let readline = rl.readline("$ ");
-                        match readline {
-                            Ok(line) => {
-                                rl.add_history_entry(line.as_str());
-                                let trimmed = line.trim_end_matches('\n').replace("\u{00A0}", " ");
-                                                                
-                                if trimmed.is_empty() {
-                                    continue;
-                                }
-                                if trimmed.contains('>') {
-                                    let (cmd_part, redir_spec) = parse_command_and_redirections(&trimmed);
-                                    run_command_with_redirections(&cmd_part, redir_spec);
-                                } else {
-                                    process_command(&trimmed);
-                                }
-
-                            },
-                            Err(ReadlineError::Interrupted) => {
-                                println!("CTRL-C");
-                                continue;
-                            },
-                            Err(ReadlineError::Eof) => {
-                                println!("CTRL-D");
-                                break;
-                            },
-                            Err(err) => {
-                                println!("Error: {:?}", err);
-                                break;
-                            }
-                        }
-                    }
+                continue;
+            },
+            Err(ReadlineError::Eof) => {
+                // Piped input may also end inside a block the validator was joining.
+                if !pending.is_empty() || rl.helper().is_some_and(|helper| *helper.unfinished.borrow()) {
+                    eprintln!("syntax error: unexpected end of file");
+                    set_last_status(2);
                 }
+                println!("CTRL-D");
+                shutdown(&mut rl, last_status());
+            },
+            Err(err) => {
+                println!("Error: {:?}", err);
+                break;
             }
         }
     }
-}
\ No newline at end of file
+}
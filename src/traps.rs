@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Mutex;
+
+use nix::fcntl::OFlag;
+use nix::libc;
+use nix::poll::{poll, PollFd, PollFlags};
+use nix::sys::signal::{self, SaFlags, SigAction, SigHandler, SigSet, Signal};
+use nix::sys::termios::{tcgetattr, tcsetattr, SetArg, Termios};
+use nix::unistd::{close, dup, dup2, pipe2, read};
+
+use crate::shell::{cancel_exit, execute_line, exit_requested, last_status, set_last_status};
+
+/// Something a trap can be set on: a signal, or one of the shell's own events.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Condition {
+    /// The shell is exiting.
+    Exit,
+    Signal(Signal),
+    /// A simple command, `[[ ]]` or `(( ))` is about to run.
+    Debug,
+    /// A command failed where `set -e` would exit.
+    Err,
+    /// A function or sourced script returned. Its trap can be set and listed,
+    /// but never runs: the shell has neither functions nor `source` yet.
+    Return,
+}
+
+/// Every condition `trap` accepts, in the order `trap -p` lists them.
+pub const CONDITIONS: &[(&str, Condition)] = &[
+    ("EXIT", Condition::Exit),
+    ("HUP", Condition::Signal(Signal::SIGHUP)),
+    ("INT", Condition::Signal(Signal::SIGINT)),
+    ("USR1", Condition::Signal(Signal::SIGUSR1)),
+    ("USR2", Condition::Signal(Signal::SIGUSR2)),
+    ("TERM", Condition::Signal(Signal::SIGTERM)),
+    ("CHLD", Condition::Signal(Signal::SIGCHLD)),
+    ("DEBUG", Condition::Debug),
+    ("ERR", Condition::Err),
+    ("RETURN", Condition::Return),
+];
+
+/// The command for each trapped condition, keyed by its index in [`CONDITIONS`].
+/// An empty command ignores the condition.
+static TRAPS: Mutex<BTreeMap<usize, String>> = Mutex::new(BTreeMap::new());
+
+/// Signals that have arrived but whose traps have not run yet, by signal number.
+static PENDING: [AtomicBool; 32] = [const { AtomicBool::new(false) }; 32];
+
+/// Set while a trap command runs, so that it does not set off traps itself.
+static IN_TRAP: AtomicBool = AtomicBool::new(false);
+
+/// The key a signal feeds the line editor to make it give up the prompt.
+pub const WAKE_KEY: char = '\x1c';
+
+/// A pipe holding [`WAKE_KEY`], as its read and write ends, or -1 before the
+/// first prompt.
+static WAKE_READ: AtomicI32 = AtomicI32::new(-1);
+static WAKE_WRITE: AtomicI32 = AtomicI32::new(-1);
+
+/// While the prompt is up, a copy of the terminal descriptor that standard
+/// input normally holds; -1 otherwise.
+static PROMPT_INPUT: AtomicI32 = AtomicI32::new(-1);
+
+/// Set once a signal has switched standard input over to the wake-up pipe.
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
+/// The terminal settings from before the line editor took over.
+static PROMPT_MODE: Mutex<Option<Termios>> = Mutex::new(None);
+
+/// The line being edited when a signal interrupted the prompt, with the cursor's byte offset.
+static INTERRUPTED_LINE: Mutex<Option<(String, usize)>> = Mutex::new(None);
+
+extern "C" fn on_signal(signal: libc::c_int) {
+    // Only flag the signal: the trap runs at the next safe point.
+    if let Some(pending) = PENDING.get(signal as usize) {
+        pending.store(true, Ordering::Relaxed);
+    }
+    // The line editor retries reads that a signal interrupts, so at the prompt
+    // it would wait for the next key before the trap could run. Hand it one
+    // instead, by pointing standard input at a pipe holding the wake-up key.
+    let wake = WAKE_READ.load(Ordering::Relaxed);
+    if wake >= 0 && PROMPT_INPUT.load(Ordering::Relaxed) >= 0 && !WOKEN.swap(true, Ordering::Relaxed) {
+        let key = WAKE_KEY as u8;
+        // SAFETY: write(2) and dup2(2) are async-signal-safe, and the pipe is
+        // empty, so the one-byte write cannot block.
+        unsafe {
+            libc::write(WAKE_WRITE.load(Ordering::Relaxed), (&key as *const u8).cast(), 1);
+            libc::dup2(wake, libc::STDIN_FILENO);
+        }
+    }
+}
+
+/// Looks up a signal specification: a name such as `INT` or `SIGINT` in any
+/// case, or a number, with 0 meaning `EXIT`.
+pub fn parse_condition(spec: &str) -> Option<usize> {
+    if let Ok(number) = spec.parse::<i32>() {
+        return CONDITIONS.iter().position(|(_, condition)| match condition {
+            Condition::Exit => number == 0,
+            Condition::Signal(signal) => *signal as i32 == number,
+            _ => false,
+        });
+    }
+    let name = spec.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    CONDITIONS.iter().position(|(condition_name, _)| *condition_name == name)
+}
+
+/// How `trap -p` names a condition: signals with their `SIG` prefix.
+pub fn display_name(index: usize) -> String {
+    match CONDITIONS[index] {
+        (name, Condition::Signal(_)) => format!("SIG{}", name),
+        (name, _) => name.to_string(),
+    }
+}
+
+/// Sets the command run for the condition at `index`, or resets it to the
+/// default with `None`. Signal dispositions follow: a handler for a command,
+/// ignored for an empty one.
+pub fn set(index: usize, command: Option<String>) {
+    if let Condition::Signal(signal) = CONDITIONS[index].1 {
+        let handler = match command.as_deref() {
+            None => SigHandler::SigDfl,
+            Some("") => SigHandler::SigIgn,
+            Some(_) => SigHandler::Handler(on_signal),
+        };
+        // Restarting interrupted system calls keeps reads and waits going.
+        let action = SigAction::new(handler, SaFlags::SA_RESTART, SigSet::empty());
+        // SAFETY: the handler only uses atomics and async-signal-safe calls.
+        let _ = unsafe { signal::sigaction(signal, &action) };
+    }
+    let mut traps = TRAPS.lock().unwrap();
+    match command {
+        Some(command) => traps.insert(index, command),
+        None => traps.remove(&index),
+    };
+}
+
+/// Every trap that is set, in listing order.
+pub fn traps() -> Vec<(usize, String)> {
+    TRAPS.lock().unwrap().iter().map(|(index, command)| (*index, command.clone())).collect()
+}
+
+fn command_for(condition: Condition) -> Option<String> {
+    let index = CONDITIONS.iter().position(|(_, c)| *c == condition)?;
+    TRAPS.lock().unwrap().get(&index).filter(|command| !command.is_empty()).cloned()
+}
+
+/// Signals the shell catches, which a child process must get back at their
+/// defaults. Ignored signals stay ignored, as POSIX requires.
+pub fn caught_signals() -> Vec<Signal> {
+    CONDITIONS
+        .iter()
+        .filter_map(|(_, condition)| match condition {
+            Condition::Signal(signal) => Some(*signal),
+            _ => None,
+        })
+        .filter(|signal| command_for(Condition::Signal(*signal)).is_some())
+        .collect()
+}
+
+/// Whether a trap command is running.
+pub fn in_trap() -> bool {
+    IN_TRAP.load(Ordering::Relaxed)
+}
+
+/// Runs a trap command, leaving `$?` as it was.
+fn run(command: &str) {
+    if IN_TRAP.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    let status = last_status();
+    execute_line(command);
+    set_last_status(status);
+    IN_TRAP.store(false, Ordering::Relaxed);
+}
+
+/// Runs the traps of any signals that arrived since the last safe point.
+pub fn run_pending() {
+    if IN_TRAP.load(Ordering::Relaxed) {
+        return;
+    }
+    for (_, condition) in CONDITIONS {
+        let Condition::Signal(signal) = condition else { continue };
+        if PENDING[*signal as usize].swap(false, Ordering::Relaxed) {
+            if let Some(command) = command_for(*condition) {
+                run(&command);
+            }
+            if exit_requested().is_some() {
+                return;
+            }
+        }
+    }
+}
+
+/// Lets signals interrupt the line editor from now until [`leave_prompt`].
+/// Only for an interactive shell, whose standard input is the terminal.
+pub fn enter_prompt() {
+    if WAKE_READ.load(Ordering::Relaxed) < 0 {
+        let Ok((read_end, write_end)) = pipe2(OFlag::O_CLOEXEC) else { return };
+        WAKE_WRITE.store(write_end, Ordering::Relaxed);
+        WAKE_READ.store(read_end, Ordering::Relaxed);
+    }
+    *PROMPT_MODE.lock().unwrap() = tcgetattr(libc::STDIN_FILENO).ok();
+    PROMPT_INPUT.store(dup(libc::STDIN_FILENO).unwrap_or(-1), Ordering::Relaxed);
+}
+
+/// Puts the terminal back on standard input, if a signal took it away, once
+/// the line editor has returned.
+pub fn leave_prompt() {
+    let input = PROMPT_INPUT.swap(-1, Ordering::Relaxed);
+    if input < 0 {
+        return;
+    }
+    if WOKEN.swap(false, Ordering::Relaxed) {
+        let _ = dup2(input, libc::STDIN_FILENO);
+        // The editor may have returned before reading the key.
+        let wake = WAKE_READ.load(Ordering::Relaxed);
+        if poll(&mut [PollFd::new(wake, PollFlags::POLLIN)], 0).is_ok_and(|ready| ready > 0) {
+            let _ = read(wake, &mut [0]);
+        }
+        // The editor could not restore the terminal through the pipe.
+        if let Some(mode) = PROMPT_MODE.lock().unwrap().as_ref() {
+            let _ = tcsetattr(libc::STDIN_FILENO, SetArg::TCSADRAIN, mode);
+        }
+    }
+    let _ = close(input);
+}
+
+/// Whether the wake-up key now being read came from a signal rather than the keyboard.
+pub fn woken() -> bool {
+    WOKEN.load(Ordering::Relaxed)
+}
+
+/// Keeps the line being edited when a signal woke the prompt, to edit again after its traps.
+pub fn set_interrupted_line(line: String, pos: usize) {
+    *INTERRUPTED_LINE.lock().unwrap() = Some((line, pos));
+}
+
+pub fn take_interrupted_line() -> Option<(String, usize)> {
+    INTERRUPTED_LINE.lock().unwrap().take()
+}
+
+/// Runs the INT trap for a Ctrl-C typed at the prompt, which the line editor
+/// reads as a key rather than a signal. Returns false if INT is not trapped.
+pub fn interrupt() -> bool {
+    match command_for(Condition::Signal(Signal::SIGINT)) {
+        Some(command) => {
+            run(&command);
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn run_debug() {
+    if let Some(command) = command_for(Condition::Debug) {
+        run(&command);
+    }
+}
+
+pub fn run_err() {
+    if let Some(command) = command_for(Condition::Err) {
+        run(&command);
+    }
+}
+
+/// Runs the EXIT trap once, with `$?` set to the exit status, and returns the
+/// status to exit with, which the trap may change by calling `exit`.
+pub fn run_exit(status: i32) -> i32 {
+    let Some(command) = command_for(Condition::Exit) else { return status };
+    set(0, None);
+    set_last_status(status);
+    cancel_exit();
+    IN_TRAP.store(true, Ordering::Relaxed);
+    execute_line(&command);
+    exit_requested().unwrap_or(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_names_and_numbers() {
+        let name_of = |spec: &str| parse_condition(spec).map(display_name);
+        assert_eq!(name_of("INT").as_deref(), Some("SIGINT"));
+        assert_eq!(name_of("sigterm").as_deref(), Some("SIGTERM"));
+        assert_eq!(name_of("Hup").as_deref(), Some("SIGHUP"));
+        assert_eq!(name_of("2").as_deref(), Some("SIGINT"));
+        assert_eq!(name_of("0").as_deref(), Some("EXIT"));
+        assert_eq!(name_of("exit").as_deref(), Some("EXIT"));
+        assert_eq!(name_of("ERR").as_deref(), Some("ERR"));
+        assert_eq!(name_of("BOGUS"), None);
+        assert_eq!(name_of("9"), None);
+    }
+
+    #[test]
+    fn stores_and_resets_return_traps() {
+        let index = parse_condition("return").unwrap();
+        assert_eq!(display_name(index), "RETURN");
+        set(index, Some("echo returned".to_string()));
+        assert!(traps().contains(&(index, "echo returned".to_string())));
+        set(index, None);
+        assert!(!traps().iter().any(|(i, _)| *i == index));
+    }
+}
@@ -1,10 +1,141 @@
+use std::env;
+use std::iter::Peekable;
+use std::str::Chars;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use nix::unistd::{getuid, User};
+
+use crate::arith;
+use crate::braces;
+use crate::commands::dirs::expand_stack_tilde;
+use crate::commands::set;
+use crate::lexer;
+use crate::shell::{is_interactive, last_status, request_exit};
+use crate::vars;
+
+/// Splits `input` into words, performing tilde and parameter expansion, field
+/// splitting and quote removal.
 pub fn parse_parameters(input: &str) -> Vec<String> {
-    let mut tokens = Vec::new();
-    let mut current = String::new();
+    expand_words(input).iter().map(|word| unquoted(word)).collect()
+}
+
+/// Like `parse_parameters`, but each character carries a flag recording whether
+/// it was quoted (or escaped), which pattern matching needs to know.
+pub fn expand_words(input: &str) -> Vec<Vec<(char, bool)>> {
+    expand(input, true)
+}
+
+/// Expands a single unexpanded word without field splitting, as is done for
+/// assignments and the operands of `[[ ]]`.
+pub fn expand_word(raw: &str) -> Vec<(char, bool)> {
+    expand(raw, false).into_iter().flatten().collect()
+}
+
+/// The plain text of an expanded word.
+pub fn unquoted(word: &[(char, bool)]) -> String {
+    word.iter().map(|&(c, _)| c).collect()
+}
+
+/// Quotes `word` for display so that the shell would read it back as one word,
+/// as `set -x` and `set` show words: unchanged when that is already the case,
+/// otherwise in single quotes.
+pub fn single_quote(word: &str) -> String {
+    let special = |c: char| c.is_whitespace() || "'\"\\|&;()<>!{}*?[]^$`".contains(c);
+    if !word.is_empty() && !word.starts_with(['~', '#']) && !word.contains(special) {
+        return word.to_string();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
+}
+
+/// Where a character of a word being expanded came from.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Origin {
+    /// Unquoted text typed on the command line.
+    Literal,
+    /// Quoted or escaped text, which is never split or treated as a pattern.
+    Quoted,
+    /// The result of an unquoted expansion, which is subject to field splitting.
+    Expanded,
+}
+
+/// Collects the words of an expansion as they are built.
+struct Words {
+    split: bool,
+    ifs: String,
+    done: Vec<Vec<(char, bool)>>,
+    current: Vec<(char, Origin)>,
+    // Quotes make a word even when nothing is inside them, as in `""`,
+    // unless all that was inside was an expansion with no fields, as in `"${empty[@]}"`.
+    quoted_word: bool,
+    vanished: bool,
+}
+
+impl Words {
+    fn push(&mut self, c: char, origin: Origin) {
+        self.current.push((c, origin));
+    }
+
+    fn extend(&mut self, text: &str, origin: Origin) {
+        self.current.extend(text.chars().map(|c| (c, origin)));
+    }
+
+    /// Adds an expansion to the word being built. Each field after the first
+    /// starts a new word; an expansion with no fields at all may remove the word.
+    fn push_expansion(&mut self, expansion: Expansion, quoted: bool) {
+        let origin = if quoted { Origin::Quoted } else { Origin::Expanded };
+        match expansion {
+            Expansion::Text(text) => self.extend(&text, origin),
+            Expansion::Fields(fields) if fields.is_empty() => self.vanished = true,
+            Expansion::Fields(fields) => {
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        // Quoted, every element is a word; unquoted, empty ones disappear.
+                        self.finish(quoted);
+                    }
+                    self.extend(field, origin);
+                }
+            }
+        }
+    }
+
+    /// Ends the current word: field splitting breaks it up at IFS characters
+    /// that came from unquoted expansions, then quotes are removed.
+    fn finish(&mut self, keep_empty: bool) {
+        let word = std::mem::take(&mut self.current);
+        let fields = if self.split {
+            let protected: Vec<(char, bool)> = word.iter().map(|&(c, origin)| (c, origin != Origin::Expanded)).collect();
+            split_fields(&protected, &self.ifs)
+        } else if word.is_empty() {
+            Vec::new()
+        } else {
+            vec![(0, word.len())]
+        };
+        if fields.is_empty() && keep_empty {
+            self.done.push(Vec::new());
+        }
+        for (start, end) in fields {
+            self.done.push(word[start..end].iter().map(|&(c, origin)| (c, origin == Origin::Quoted)).collect());
+        }
+    }
+
+    fn end_word(&mut self) {
+        self.finish(self.quoted_word && !self.vanished);
+        self.quoted_word = false;
+        self.vanished = false;
+    }
+}
+
+fn expand(input: &str, split: bool) -> Vec<Vec<(char, bool)>> {
+    let mut words = Words { split, ifs: ifs(), done: Vec::new(), current: Vec::new(), quoted_word: false, vanished: false };
     let mut chars = input.chars().peekable();
     let mut in_quotes: Option<char> = None; // None means not in quotes; Some(q) means in a quoted segment with delimiter q
+    // Whether a `~` here would start a tilde prefix: at the start of a word, or
+    // after the `=` or a `:` in the value of an assignment.
+    let mut tilde_allowed = true;
+    let mut in_assignment = false;
 
     while let Some(c) = chars.next() {
+        let tilde_here = std::mem::replace(&mut tilde_allowed, false);
         if let Some(q) = in_quotes {
             // We're inside a quoted segment.
             if c == q {
@@ -15,45 +146,712 @@ pub fn parse_parameters(input: &str) -> Vec<String> {
                 if let Some(&_next_char) = chars.peek() {
                     if _next_char == '\\' || _next_char == '$' || _next_char == '"' || _next_char == '\n' {
                         // Consume next char and push it.
-                        current.push(chars.next().unwrap());
+                        words.push(chars.next().unwrap(), Origin::Quoted);
                     } else {
                         // Otherwise, the backslash is literal.
-                        current.push(c);
+                        words.push(c, Origin::Quoted);
                     }
                 } else {
-                    current.push(c);
+                    words.push(c, Origin::Quoted);
                 }
+            } else if q == '"' && c == '$' {
+                let expansion = expand_parameter(&mut chars, true);
+                words.push_expansion(expansion, true);
             } else {
                 // For single quotes, or any other char inside double quotes that isn’t a special backslash, copy literally.
-                current.push(c);
+                words.push(c, Origin::Quoted);
             }
         } else {
             // We're not inside any quotes.
             match c {
                 '"' | '\'' => {
                     in_quotes = Some(c);
+                    words.quoted_word = true;
+                },
+                '$' => {
+                    let expansion = expand_parameter(&mut chars, false);
+                    words.push_expansion(expansion, false);
+                },
+                '~' if tilde_here => match expand_tilde(&mut chars, in_assignment) {
+                    Some(dir) => words.extend(&dir, Origin::Quoted),
+                    None => words.push('~', Origin::Literal),
                 },
                 '\\' => {
                     // Outside quotes, backslash always escapes the next character.
-                    if let Some(&next_char) = chars.peek() {
-                        current.push(chars.next().unwrap());
+                    if chars.peek().is_some() {
+                        words.push(chars.next().unwrap(), Origin::Quoted);
                     }
                 },
-                c if c.is_whitespace() => {
-                    if !current.is_empty() {
-                        tokens.push(current);
-                        current = String::new();
-                    }
+                // Without field splitting, as in a subscript, blanks are kept.
+                c if c.is_whitespace() && words.split => {
+                    tilde_allowed = true;
+                    in_assignment = false;
+                    words.end_word();
                     // Skip additional whitespace.
                 },
+                '=' if !in_assignment => {
+                    let lhs: String = words.current.iter().map(|&(c, _)| c).collect();
+                    in_assignment = words.current.iter().all(|&(_, origin)| origin == Origin::Literal)
+                        && lexer::is_assignment_prefix(&format!("{}=", lhs));
+                    tilde_allowed = in_assignment;
+                    words.push('=', Origin::Literal);
+                }
+                ':' if in_assignment => {
+                    tilde_allowed = true;
+                    words.push(':', Origin::Literal);
+                }
                 other => {
-                    current.push(other);
+                    words.push(other, Origin::Literal);
                 }
             }
         }
     }
-    if !current.is_empty() {
-        tokens.push(current);
+    words.end_word();
+    words.done
+}
+
+/// Set when an expansion fails, e.g. on division by zero; the command must then not run.
+static EXPANSION_FAILED: AtomicBool = AtomicBool::new(false);
+
+pub fn expansion_failed() -> bool {
+    EXPANSION_FAILED.load(Ordering::Relaxed)
+}
+
+/// Reports, and clears, whether an expansion failed since the last call.
+pub fn take_expansion_error() -> bool {
+    EXPANSION_FAILED.swap(false, Ordering::Relaxed)
+}
+
+/// The value of an unset parameter: empty, unless `set -u` makes referring to
+/// one an error, which also ends a non-interactive shell.
+fn unbound(name: &str) -> String {
+    if set::is_enabled("nounset") {
+        eprintln!("{}: unbound variable", name);
+        EXPANSION_FAILED.store(true, Ordering::Relaxed);
+        if !is_interactive() {
+            request_exit(1);
+        }
+    }
+    String::new()
+}
+
+/// `$@` and `$*`: the positional parameters as separate fields, except for
+/// `"$*"`, which joins them with the first character of IFS.
+fn all_positional(star: bool, quoted: bool) -> Expansion {
+    let params = vars::positional();
+    if star && quoted {
+        let separator = ifs().chars().next().map(String::from).unwrap_or_default();
+        Expansion::Text(params.join(&separator))
+    } else {
+        Expansion::Fields(params)
+    }
+}
+
+/// What a `$` expansion produced: usually a single string, but `${arr[@]}`
+/// yields one field per element, even inside double quotes.
+enum Expansion {
+    Text(String),
+    Fields(Vec<String>),
+}
+
+impl Expansion {
+    fn joined(self) -> String {
+        match self {
+            Expansion::Text(text) => text,
+            Expansion::Fields(fields) => fields.join(" "),
+        }
+    }
+}
+
+/// Expands the parameter following a `$`: `$?`, `$$`, `$0`, `$1` to `$9`, `$#`,
+/// `$@`, `$*`, `$-`, `$NAME`, `${...}` or an arithmetic expansion `$(( ))`. A `$` that does not start a parameter
+/// is kept literally. `quoted` tells whether the `$` was inside double quotes.
+fn expand_parameter(chars: &mut Peekable<Chars>, quoted: bool) -> Expansion {
+    if chars.clone().take(2).eq(['(', '(']) {
+        chars.nth(1);
+        let mut expression = String::new();
+        let mut depth = 0;
+        while let Some(c) = chars.next() {
+            match c {
+                '(' => depth += 1,
+                ')' if depth == 0 && chars.peek() == Some(&')') => {
+                    chars.next();
+                    break;
+                }
+                ')' => depth -= 1,
+                _ => {}
+            }
+            expression.push(c);
+        }
+        return Expansion::Text(match evaluate_arithmetic(&expression) {
+            Some(value) => value.to_string(),
+            None => String::new(),
+        });
+    }
+    let text = match chars.peek().copied() {
+        Some('?') => {
+            chars.next();
+            last_status().to_string()
+        }
+        Some('$') => {
+            chars.next();
+            std::process::id().to_string()
+        }
+        Some('0') => {
+            chars.next();
+            env::args().next().unwrap_or_default()
+        }
+        Some(c @ '1'..='9') => {
+            chars.next();
+            let n = c as usize - '0' as usize;
+            vars::positional_param(n).unwrap_or_else(|| unbound(&format!("${}", n)))
+        }
+        Some('#') => {
+            chars.next();
+            vars::positional().len().to_string()
+        }
+        Some(c @ ('@' | '*')) => {
+            chars.next();
+            return all_positional(c == '*', quoted);
+        }
+        Some('-') => {
+            chars.next();
+            set::flags()
+        }
+        Some('{') => {
+            chars.next();
+            let mut body = String::new();
+            let mut depth = 0;
+            for c in chars.by_ref() {
+                match c {
+                    '{' => depth += 1,
+                    '}' if depth == 0 => break,
+                    '}' => depth -= 1,
+                    _ => {}
+                }
+                body.push(c);
+            }
+            return expand_braced(&body, quoted);
+        }
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                name.push(c);
+                chars.next();
+            }
+            vars::get(&name).unwrap_or_else(|| unbound(&name))
+        }
+        _ => "$".to_string(),
+    };
+    Expansion::Text(text)
+}
+
+/// Expands the inside of `${...}`: a special or positional parameter, a plain
+/// name, an array element `name[sub]`, all elements with `name[@]` or
+/// `name[*]`, a length `#name`, the keys `!name[@]`, or any of these followed
+/// by an `:offset:length` slice. A parameter may also be followed by `-word`,
+/// `=word`, `?word` or `+word`, which test whether it is set, or with a colon
+/// whether it is set and not empty.
+fn expand_braced(body: &str, quoted: bool) -> Expansion {
+    match body {
+        "?" => return Expansion::Text(last_status().to_string()),
+        "$" => return Expansion::Text(std::process::id().to_string()),
+        "#" | "#@" | "#*" => return Expansion::Text(vars::positional().len().to_string()),
+        "@" | "*" => return all_positional(body == "*", quoted),
+        "-" => return Expansion::Text(set::flags()),
+        _ if body.bytes().all(|b| b.is_ascii_digit()) => {
+            if let Some(n) = body.parse::<usize>().ok().filter(|&n| n > 0) {
+                return Expansion::Text(vars::positional_param(n).unwrap_or_else(|| unbound(body)));
+            }
+        }
+        _ => {}
+    }
+    let bad_substitution = || {
+        eprintln!("${{{}}}: bad substitution", body);
+        EXPANSION_FAILED.store(true, Ordering::Relaxed);
+        Expansion::Text(String::new())
+    };
+    let (prefix, rest) = match body.strip_prefix(['#', '!']) {
+        Some(rest) if !rest.is_empty() => (body.chars().next(), rest),
+        _ => (None, body),
+    };
+    let name_end = if rest.starts_with(|c: char| c.is_ascii_digit()) {
+        rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())
+    } else {
+        rest.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(rest.len())
+    };
+    let name = &rest[..name_end];
+    let positional = !name.is_empty() && name.bytes().all(|b| b.is_ascii_digit());
+    if !positional && !vars::is_valid_name(name) {
+        return bad_substitution();
+    }
+    let mut rest = &rest[name_end..];
+    let mut subscript = None;
+    if rest.starts_with('[') && !positional {
+        let Some(close) = rest.find(']') else { return bad_substitution() };
+        subscript = Some(&rest[1..close]);
+        rest = &rest[close + 1..];
+    }
+    let all = matches!(subscript, Some("@" | "*"));
+    let parameter = &body[prefix.is_some() as usize..body.len() - rest.len()];
+
+    // The test operators come before slices, so that `${x:-1}` is a default value.
+    let colon = rest.starts_with(':');
+    let test = rest[colon as usize..].strip_prefix(['-', '=', '?', '+']);
+    if let Some(word) = test {
+        if prefix.is_some() {
+            return bad_substitution();
+        }
+        let operator = rest.as_bytes()[colon as usize];
+        let current = if all {
+            let values: Vec<String> = vars::elements(name).into_iter().map(|(_, value)| value).collect();
+            (!values.is_empty()).then(|| join_fields(values, subscript == Some("*") && quoted))
+        } else {
+            element(name, subscript).map(Expansion::Text)
+        };
+        let current = current.filter(|current| {
+            !colon
+                || match current {
+                    Expansion::Text(text) => !text.is_empty(),
+                    Expansion::Fields(fields) => fields.iter().any(|field| !field.is_empty()),
+                }
+        });
+        let word = || unquoted(&expand_word(word));
+        return match (operator, current) {
+            (b'+', Some(_)) => Expansion::Text(word()),
+            (b'+', None) => Expansion::Text(String::new()),
+            (_, Some(current)) => current,
+            (b'-', None) => Expansion::Text(word()),
+            (b'=', None) if positional || all => {
+                eprintln!("${}: cannot assign in this way", parameter);
+                EXPANSION_FAILED.store(true, Ordering::Relaxed);
+                Expansion::Text(String::new())
+            }
+            (b'=', None) => {
+                let value = word();
+                match subscript {
+                    Some(subscript) => match resolve_subscript(name, subscript) {
+                        Some(key) => vars::set_element(name, key, &value),
+                        None => return Expansion::Text(String::new()),
+                    },
+                    None => vars::set(name, &value),
+                }
+                Expansion::Text(value)
+            }
+            _ => {
+                let message = word();
+                let message = if message.is_empty() { "parameter null or not set".to_string() } else { message };
+                eprintln!("{}: {}", parameter, message);
+                EXPANSION_FAILED.store(true, Ordering::Relaxed);
+                if !is_interactive() {
+                    request_exit(1);
+                }
+                Expansion::Text(String::new())
+            }
+        };
+    }
+    let slice = match rest.strip_prefix(':') {
+        Some(slice) if prefix.is_none() && !slice.is_empty() => Some(slice),
+        None if rest.is_empty() => None,
+        _ => return bad_substitution(),
+    };
+
+    let join = |fields| join_fields(fields, subscript == Some("*") && quoted);
+    match (prefix, subscript) {
+        (Some('#'), _) if all => Expansion::Text(vars::elements(name).len().to_string()),
+        (Some('#'), _) => {
+            let value = element(name, subscript).unwrap_or_else(|| unbound(&body[1..]));
+            Expansion::Text(value.chars().count().to_string())
+        }
+        (Some('!'), _) if all => join(vars::elements(name).into_iter().map(|(key, _)| key).collect()),
+        (Some(_), _) => bad_substitution(),
+        _ if all => {
+            let mut elements = vars::elements(name);
+            if let Some(slice) = slice {
+                let Some((offset, length)) = slice_bounds(slice) else { return Expansion::Text(String::new()) };
+                // Offsets count from an index, not a position, for indexed arrays.
+                let indexed = !vars::is_assoc(name);
+                let key_of = |(i, (key, _)): (usize, &(String, String))| if indexed { key.parse().unwrap_or(0) } else { i as i64 };
+                let end = elements.iter().enumerate().map(key_of).max().map_or(0, |k| k + 1);
+                let offset = if offset < 0 { end + offset } else { offset };
+                if offset < 0 || length.is_some_and(|length| length < 0) {
+                    eprintln!("{}: substring expression < 0", slice);
+                    EXPANSION_FAILED.store(true, Ordering::Relaxed);
+                    return Expansion::Text(String::new());
+                }
+                let start = elements.iter().enumerate().map(key_of).position(|k| k >= offset).unwrap_or(elements.len());
+                elements.drain(..start);
+                elements.truncate(length.map_or(usize::MAX, |length| length as usize));
+            }
+            join(elements.into_iter().map(|(_, value)| value).collect())
+        }
+        _ => {
+            let value = element(name, subscript).unwrap_or_else(|| unbound(&body[..body.len() - rest.len()]));
+            match slice {
+                Some(slice) => Expansion::Text(substring(&value, slice)),
+                None => Expansion::Text(value),
+            }
+        }
+    }
+}
+
+/// The elements of an array expansion as fields, or for `"${arr[*]}"`, as a
+/// single word joined by the first IFS character.
+fn join_fields(fields: Vec<String>, star_quoted: bool) -> Expansion {
+    if star_quoted {
+        let separator = ifs().chars().next().map(String::from).unwrap_or_default();
+        Expansion::Text(fields.join(&separator))
+    } else {
+        Expansion::Fields(fields)
+    }
+}
+
+/// Looks up `name`, `name[subscript]` or a positional parameter such as `1`.
+fn element(name: &str, subscript: Option<&str>) -> Option<String> {
+    match subscript {
+        None if name == "0" => env::args().next(),
+        None if name.bytes().all(|b| b.is_ascii_digit()) => vars::positional_param(name.parse().ok()?),
+        None => vars::get(name),
+        Some(subscript) => vars::get_element(name, &resolve_subscript(name, subscript)?),
+    }
+}
+
+/// Turns a raw subscript into a key: expanded text for an associative array,
+/// otherwise an arithmetic index, where a negative one counts back from the end.
+pub fn resolve_subscript(name: &str, subscript: &str) -> Option<vars::Key> {
+    if vars::is_assoc(name) {
+        return Some(vars::Key::Name(unquoted(&expand_word(subscript))));
+    }
+    let index = evaluate_arithmetic(subscript)?;
+    if index >= 0 {
+        return Some(vars::Key::Index(index as usize));
+    }
+    match usize::try_from(vars::next_index(name) as i64 + index) {
+        Ok(index) => Some(vars::Key::Index(index)),
+        Err(_) => {
+            eprintln!("{}[{}]: bad array subscript", name, subscript);
+            EXPANSION_FAILED.store(true, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+/// Evaluates the `offset` and optional `length` of a `:offset:length` slice.
+fn slice_bounds(slice: &str) -> Option<(i64, Option<i64>)> {
+    let (offset, length) = match slice.split_once(':') {
+        Some((offset, length)) => (offset, Some(length)),
+        None => (slice, None),
+    };
+    let offset = evaluate_arithmetic(offset)?;
+    let length = match length {
+        Some(length) => Some(evaluate_arithmetic(length)?),
+        None => None,
+    };
+    Some((offset, length))
+}
+
+/// `${name:offset:length}` on a string. Negative offsets count from the end,
+/// and a negative length is an end position counted from the end.
+fn substring(value: &str, slice: &str) -> String {
+    let Some((offset, length)) = slice_bounds(slice) else { return String::new() };
+    let chars: Vec<char> = value.chars().collect();
+    let len = chars.len() as i64;
+    let start = if offset < 0 { (len + offset).max(0) } else { offset.min(len) };
+    let end = match length {
+        None => len,
+        Some(length) if length < 0 => len + length,
+        Some(length) => (start + length).min(len),
+    };
+    if end < start {
+        if length.is_some_and(|length| length < 0) {
+            eprintln!("{}: substring expression < 0", slice.split(':').nth(1).unwrap_or_default());
+            EXPANSION_FAILED.store(true, Ordering::Relaxed);
+        }
+        return String::new();
+    }
+    chars[start as usize..end as usize].iter().collect()
+}
+
+/// Expands the tilde prefix following a `~`: up to the next `/` (or `:` in an
+/// assignment), it may be empty for `$HOME`, `+` or `-` for `$PWD` and `$OLDPWD`,
+/// a directory stack index such as `2` or `-1`, or a user name. Returns `None`,
+/// consuming nothing, if the prefix is quoted or names nothing.
+fn expand_tilde(chars: &mut Peekable<Chars>, in_assignment: bool) -> Option<String> {
+    let prefix: String = chars
+        .clone()
+        .take_while(|&c| c != '/' && !c.is_whitespace() && !(in_assignment && c == ':'))
+        .collect();
+    if prefix.contains(['\'', '"', '\\', '$']) {
+        return None;
+    }
+    let dir = match prefix.as_str() {
+        "" => vars::get("HOME").or_else(|| {
+            User::from_uid(getuid()).ok().flatten().map(|user| user.dir.display().to_string())
+        }),
+        "+" => vars::get("PWD"),
+        "-" => vars::get("OLDPWD"),
+        _ if prefix.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-') => {
+            expand_stack_tilde(&format!("~{}", prefix)).map(|dir| dir.display().to_string())
+        }
+        name => User::from_name(name).ok().flatten().map(|user| user.dir.display().to_string()),
+    }?;
+    for _ in prefix.chars() {
+        chars.next();
+    }
+    Some(dir)
+}
+
+/// The current field separators: `$IFS`, or space, tab and newline when unset.
+pub fn ifs() -> String {
+    vars::get("IFS").unwrap_or_else(|| " \t\n".to_string())
+}
+
+/// Splits `text` into fields on the characters of `ifs`, returning each field's
+/// `(start, end)` span. Characters flagged as quoted never separate fields.
+///
+/// Following POSIX, runs of IFS whitespace act as a single separator and are
+/// trimmed at both ends, while every other IFS character ends exactly one field,
+/// so `a::b` with `IFS=:` has an empty middle field.
+pub fn split_fields(text: &[(char, bool)], ifs: &str) -> Vec<(usize, usize)> {
+    let is_sep = |i: usize| !text[i].1 && ifs.contains(text[i].0);
+    let is_ws_sep = |i: usize| is_sep(i) && text[i].0.is_whitespace();
+    let mut fields = Vec::new();
+    let mut i = 0;
+    while i < text.len() && is_ws_sep(i) {
+        i += 1;
+    }
+    while i < text.len() {
+        let start = i;
+        while i < text.len() && !is_sep(i) {
+            i += 1;
+        }
+        fields.push((start, i));
+        // Consume the separator: surrounding whitespace plus at most one other IFS character.
+        while i < text.len() && is_ws_sep(i) {
+            i += 1;
+        }
+        if i < text.len() && is_sep(i) && !is_ws_sep(i) {
+            i += 1;
+            while i < text.len() && is_ws_sep(i) {
+                i += 1;
+            }
+        }
+    }
+    fields
+}
+
+/// Expands parameters inside an arithmetic expression and evaluates it,
+/// printing the error and flagging the expansion as failed if it is invalid.
+pub fn evaluate_arithmetic(expression: &str) -> Option<i64> {
+    // Only parameters are expanded and quotes removed: `~` and spaces mean something here.
+    let mut expanded = String::new();
+    let mut chars = expression.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '$' => expanded.push_str(&expand_parameter(&mut chars, false).joined()),
+            '"' | '\'' => {}
+            c => expanded.push(c),
+        }
+    }
+    let expression = expanded;
+    match arith::evaluate(&expression) {
+        Ok(value) => Some(value),
+        Err(message) => {
+            eprintln!("{}", message);
+            EXPANSION_FAILED.store(true, Ordering::Relaxed);
+            None
+        }
+    }
+}
+
+/// Expands an assignment word as a single field. A subscript is left as written,
+/// to be expanded once when the assignment resolves it.
+pub fn expand_assignment(word: &str) -> String {
+    match vars::parse_assignment(word) {
+        Some(assignment) if assignment.subscript.is_some() => {
+            let target = &word[..word.len() - assignment.value.len()];
+            format!("{}{}", target, unquoted(&expand_word(assignment.value)))
+        }
+        _ => unquoted(&expand_word(word)),
+    }
+}
+
+/// Performs a `NAME=value`, `NAME+=value` or `NAME[subscript]=value` assignment,
+/// returning false if the subscript is invalid.
+pub fn assign(assignment: &vars::Assignment) -> bool {
+    let name = assignment.name;
+    match assignment.subscript {
+        Some(subscript) => {
+            let Some(key) = resolve_subscript(name, subscript) else { return false };
+            let mut value = String::new();
+            if assignment.append {
+                value = vars::get_element(name, &key).unwrap_or_default();
+            }
+            value.push_str(assignment.value);
+            vars::set_element(name, key, &value);
+        }
+        None => {
+            let mut value = String::new();
+            if assignment.append {
+                value = vars::get(name).unwrap_or_default();
+            }
+            value.push_str(assignment.value);
+            vars::set(name, &value);
+        }
+    }
+    true
+}
+
+/// Recognizes an unexpanded compound assignment `NAME=(...)` or `NAME+=(...)`,
+/// returning the name, whether it appends, and the text between the parentheses.
+pub fn compound_assignment(raw: &str) -> Option<(&str, bool, &str)> {
+    let assignment = vars::parse_assignment(raw)?;
+    if assignment.subscript.is_some() {
+        return None;
+    }
+    let inner = assignment.value.strip_prefix('(')?.strip_suffix(')')?;
+    Some((assignment.name, assignment.append, inner))
+}
+
+/// Assigns the words of a compound assignment to an array. Words of the form
+/// `[key]=value` set that key; others take the next index after the last one set.
+pub fn assign_compound(name: &str, append: bool, inner: &str) -> bool {
+    let assoc = vars::is_assoc(name);
+    if !append {
+        let value = if assoc { vars::Value::Assoc(Default::default()) } else { vars::Value::Indexed(Default::default()) };
+        vars::set_value(name, value);
+    } else if !assoc && vars::value(name).is_none() {
+        vars::set_value(name, vars::Value::Indexed(Default::default()));
+    }
+    let mut next = vars::next_index(name);
+    let words = lexer::tokenize(inner)
+        .tokens
+        .into_iter()
+        .filter(|token| token.kind == lexer::TokenKind::Word)
+        .flat_map(|token| braces::expand(&token.text))
+        .flat_map(|raw| expand_words(&raw));
+    for word in words {
+        let text = unquoted(&word);
+        let keyed = match word.first() {
+            Some(('[', false)) => text[1..].split_once("]=").map(|(key, value)| (key.to_string(), value.to_string())),
+            _ => None,
+        };
+        match keyed {
+            Some((key, value)) if assoc => vars::set_element(name, vars::Key::Name(key), &value),
+            Some((key, value)) => match arith::evaluate(&key) {
+                Ok(index) if index >= 0 => {
+                    vars::set_element(name, vars::Key::Index(index as usize), &value);
+                    next = index as usize + 1;
+                }
+                Ok(_) => {
+                    eprintln!("{}[{}]: bad array subscript", name, key);
+                    return false;
+                }
+                Err(message) => {
+                    eprintln!("{}", message);
+                    return false;
+                }
+            },
+            None if assoc => {
+                eprintln!("{}: {}: must use subscript when assigning associative array", name, text);
+                return false;
+            }
+            None => {
+                vars::set_element(name, vars::Key::Index(next), &text);
+                next += 1;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn assigns_and_expands_indexed_arrays() {
+        assert!(assign_compound("util_test_list", false, "a 'b c' [5]=x y"));
+        assert_eq!(parse_parameters("\"${util_test_list[1]}\" ${util_test_list[6]} ${#util_test_list[@]}"), ["b c", "y", "4"]);
+        assert_eq!(parse_parameters("\"${util_test_list[@]}\""), ["a", "b c", "x", "y"]);
+        assert_eq!(parse_parameters("${util_test_list[-1]} ${util_test_list}"), ["y", "a"]);
+        assert_eq!(parse_parameters("${!util_test_list[@]}"), ["0", "1", "5", "6"]);
+        assert!(assign_compound("util_test_list", true, "z"));
+        assert_eq!(parse_parameters("${util_test_list[7]}"), ["z"]);
+    }
+
+    #[test]
+    fn assigns_associative_elements() {
+        vars::declare_array("util_test_map", true).unwrap();
+        assert!(assign_compound("util_test_map", false, "[key]=value [other]=2"));
+        assert!(assign(&vars::parse_assignment("util_test_map[key]+=s").unwrap()));
+        assert_eq!(parse_parameters("${util_test_map[key]} ${#util_test_map[@]}"), ["values", "2"]);
+        assert!(!assign_compound("util_test_map", false, "loose"));
+    }
+
+    #[test]
+    fn expands_tilde_prefixes() {
+        let home = vars::get("HOME").unwrap_or_default();
+        let pwd = std::env::current_dir().unwrap().display().to_string();
+        vars::set("PWD", &pwd);
+        vars::set("OLDPWD", "/previous/dir");
+        assert_eq!(parse_parameters("~ ~/a ~+/b ~-"), [home.clone(), format!("{}/a", home), format!("{}/b", pwd), "/previous/dir".to_string()]);
+        assert_eq!(parse_parameters("~root/x ~shell_test_no_such_user"), ["/root/x", "~shell_test_no_such_user"]);
+        assert_eq!(parse_parameters("'~'/a \\~ a~ ~\"x\""), ["~/a", "~", "a~", "~x"]);
+        assert_eq!(parse_parameters("PATH=~/bin:~/sbin"), [format!("PATH={}/bin:{}/sbin", home, home)]);
+    }
+
+    fn fields(text: &str, ifs: &str) -> Vec<String> {
+        let text: Vec<(char, bool)> = text.chars().map(|c| (c, false)).collect();
+        split_fields(&text, ifs).into_iter().map(|(start, end)| unquoted(&text[start..end])).collect()
+    }
+
+    #[test]
+    fn splits_fields_like_posix() {
+        assert_eq!(fields("  a  b\tc ", " \t\n"), ["a", "b", "c"]);
+        assert_eq!(fields("a::b:", ":"), ["a", "", "b"]);
+        assert_eq!(fields(" a : b ", " :"), ["a", "b"]);
+        assert_eq!(fields("a : : b", " :"), ["a", "", "b"]);
+        assert_eq!(fields("a b", ""), ["a b"]);
+        assert!(fields("   ", " ").is_empty());
+
+        let quoted = [('a', false), (' ', true), ('b', false), (' ', false), ('c', false)];
+        assert_eq!(split_fields(&quoted, " "), [(0, 3), (4, 5)]);
+    }
+
+    #[test]
+    fn subscripts_may_hold_spaces() {
+        vars::declare_array("util_test_map", true).unwrap();
+        vars::set_element("util_test_map", vars::Key::Name("two words".into()), "2");
+        vars::set("util_test_key", "two words");
+        assert_eq!(parse_parameters("${util_test_map[two words]}"), ["2"]);
+        assert_eq!(parse_parameters("\"${util_test_map[$util_test_key]}\""), ["2"]);
+        assert_eq!(expand_assignment("util_test_map[$util_test_key]=$util_test_key"), "util_test_map[$util_test_key]=two words");
+    }
+
+    #[test]
+    fn tests_whether_parameters_are_set() {
+        vars::set("util_test_empty", "");
+        assert_eq!(parse_parameters("${util_test_unset:-a b}"), ["a", "b"]);
+        assert_eq!(parse_parameters("\"${util_test_unset:-a b}\""), ["a b"]);
+        assert_eq!(parse_parameters("${util_test_empty:-default}"), ["default"]);
+        assert_eq!(parse_parameters("x${util_test_empty-default}x"), ["xx"]);
+        assert_eq!(parse_parameters("${util_test_empty:+alt} ${util_test_empty+alt}"), ["alt"]);
+        assert_eq!(parse_parameters("${util_test_unset+alt}x"), ["x"]);
+    }
+
+    #[test]
+    fn assigns_defaults_and_reports_unset_parameters() {
+        assert_eq!(parse_parameters("${util_test_assigned:=first} ${util_test_assigned:=second}"), ["first", "first"]);
+        assert_eq!(vars::get("util_test_assigned").as_deref(), Some("first"));
+
+        assert_eq!(parse_parameters("${util_test_required:?}"), Vec::<String>::new());
+        assert!(take_expansion_error());
+        crate::shell::cancel_exit();
+        vars::set("util_test_required", "ok");
+        assert_eq!(parse_parameters("${util_test_required:?missing}"), ["ok"]);
     }
-    tokens
-}
\ No newline at end of file
+}
@@ -0,0 +1,292 @@
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::sync::{LazyLock, Mutex};
+
+/// The value of a shell variable that is not in the environment.
+#[derive(Clone, Debug)]
+pub enum Value {
+    Scalar(String),
+    Indexed(BTreeMap<usize, String>),
+    Assoc(BTreeMap<String, String>),
+}
+
+impl Value {
+    /// What `$name` yields: the scalar itself, or element 0 of an array.
+    fn as_scalar(&self) -> Option<String> {
+        self.get(&Key::Index(0))
+    }
+
+    fn get(&self, key: &Key) -> Option<String> {
+        match (self, key) {
+            (Value::Scalar(s), Key::Index(0)) => Some(s.clone()),
+            (Value::Indexed(items), Key::Index(i)) => items.get(i).cloned(),
+            (Value::Assoc(items), Key::Name(k)) => items.get(k).cloned(),
+            (Value::Assoc(items), Key::Index(i)) => items.get(&i.to_string()).cloned(),
+            _ => None,
+        }
+    }
+}
+
+/// A resolved array subscript: an index into an indexed array, or an associative array key.
+#[derive(Clone, Debug)]
+pub enum Key {
+    Index(usize),
+    Name(String),
+}
+
+/// Shell-only variables. Exported variables live in the process environment so
+/// child processes inherit them; a name is never in both places at once.
+static SHELL_VARS: LazyLock<Mutex<HashMap<String, Value>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// `$1`, `$2` and so on, as set by `set --`.
+static POSITIONAL: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+pub fn positional() -> Vec<String> {
+    POSITIONAL.lock().unwrap().clone()
+}
+
+/// `$n` for n of 1 or more.
+pub fn positional_param(n: usize) -> Option<String> {
+    POSITIONAL.lock().unwrap().get(n.checked_sub(1)?).cloned()
+}
+
+pub fn set_positional(params: Vec<String>) {
+    *POSITIONAL.lock().unwrap() = params;
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A `NAME=value`, `NAME+=value` or `NAME[subscript]=value` word, split into its parts.
+pub struct Assignment<'a> {
+    pub name: &'a str,
+    /// The unevaluated text between the brackets, if any.
+    pub subscript: Option<&'a str>,
+    /// Set for `+=`, which appends to the current value.
+    pub append: bool,
+    pub value: &'a str,
+}
+
+/// Splits an assignment word, if that is what it is.
+pub fn parse_assignment(word: &str) -> Option<Assignment<'_>> {
+    let name_end = word.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).unwrap_or(word.len());
+    let name = &word[..name_end];
+    if !is_valid_name(name) {
+        return None;
+    }
+    let mut rest = &word[name_end..];
+    let mut subscript = None;
+    if rest.starts_with('[') {
+        let close = rest.find(']')?;
+        subscript = Some(&rest[1..close]);
+        rest = &rest[close + 1..];
+    }
+    let (append, value) = match rest.strip_prefix("+=") {
+        Some(value) => (true, value),
+        None => (false, rest.strip_prefix('=')?),
+    };
+    Some(Assignment { name, subscript, append, value })
+}
+
+pub fn get(name: &str) -> Option<String> {
+    if let Some(value) = SHELL_VARS.lock().unwrap().get(name) {
+        return value.as_scalar();
+    }
+    env::var(name).ok()
+}
+
+/// Assigns a scalar, keeping an exported variable exported. Assigning to an
+/// array without a subscript sets its element 0.
+pub fn set(name: &str, value: &str) {
+    let mut vars = SHELL_VARS.lock().unwrap();
+    match vars.get_mut(name) {
+        Some(Value::Indexed(items)) => {
+            items.insert(0, value.to_string());
+        }
+        Some(Value::Assoc(items)) => {
+            items.insert("0".to_string(), value.to_string());
+        }
+        None if env::var_os(name).is_some() => env::set_var(name, value),
+        _ => {
+            vars.insert(name.to_string(), Value::Scalar(value.to_string()));
+        }
+    }
+}
+
+/// Assigns an indexed array. Arrays cannot be exported, so any environment copy is dropped.
+pub fn set_array(name: &str, items: Vec<String>) {
+    set_value(name, Value::Indexed(items.into_iter().enumerate().collect()));
+}
+
+/// Replaces a variable's whole value.
+pub fn set_value(name: &str, value: Value) {
+    env::remove_var(name);
+    SHELL_VARS.lock().unwrap().insert(name.to_string(), value);
+}
+
+/// A variable's value, with exported variables reported as scalars.
+pub fn value(name: &str) -> Option<Value> {
+    if let Some(value) = SHELL_VARS.lock().unwrap().get(name) {
+        return Some(value.clone());
+    }
+    env::var(name).ok().map(Value::Scalar)
+}
+
+pub fn is_assoc(name: &str) -> bool {
+    matches!(SHELL_VARS.lock().unwrap().get(name), Some(Value::Assoc(_)))
+}
+
+pub fn get_element(name: &str, key: &Key) -> Option<String> {
+    value(name)?.get(key)
+}
+
+/// Sets one element, turning a scalar (or unset variable) into an indexed array
+/// whose element 0 is the old value.
+pub fn set_element(name: &str, key: Key, element: &str) {
+    let mut value = value(name).unwrap_or(Value::Indexed(BTreeMap::new()));
+    if let Value::Scalar(s) = value {
+        value = Value::Indexed(BTreeMap::from([(0, s)]));
+    }
+    match (&mut value, key) {
+        (Value::Indexed(items), Key::Index(i)) => {
+            items.insert(i, element.to_string());
+        }
+        (Value::Assoc(items), Key::Name(k)) => {
+            items.insert(k, element.to_string());
+        }
+        (Value::Assoc(items), Key::Index(i)) => {
+            items.insert(i.to_string(), element.to_string());
+        }
+        _ => return,
+    }
+    set_value(name, value);
+}
+
+/// Every element as `(key, value)`, in index order. A scalar is a one-element array.
+pub fn elements(name: &str) -> Vec<(String, String)> {
+    match value(name) {
+        Some(Value::Scalar(s)) => vec![("0".to_string(), s)],
+        Some(Value::Indexed(items)) => items.into_iter().map(|(i, v)| (i.to_string(), v)).collect(),
+        Some(Value::Assoc(items)) => items.into_iter().collect(),
+        None => Vec::new(),
+    }
+}
+
+/// One past the highest index in use, where `arr+=(x)` appends.
+pub fn next_index(name: &str) -> usize {
+    match value(name) {
+        Some(Value::Scalar(_)) => 1,
+        Some(Value::Indexed(items)) => items.keys().next_back().map_or(0, |i| i + 1),
+        _ => 0,
+    }
+}
+
+/// Gives `name` an array type, converting a scalar into element 0.
+pub fn declare_array(name: &str, assoc: bool) -> Result<(), String> {
+    let value = match (value(name), assoc) {
+        (Some(Value::Indexed(_)), true) => return Err("cannot convert indexed to associative array".to_string()),
+        (Some(Value::Assoc(_)), false) => return Err("cannot convert associative to indexed array".to_string()),
+        (Some(Value::Indexed(_) | Value::Assoc(_)), _) => return Ok(()),
+        (Some(Value::Scalar(s)), true) => Value::Assoc(BTreeMap::from([("0".to_string(), s)])),
+        (Some(Value::Scalar(s)), false) => Value::Indexed(BTreeMap::from([(0, s)])),
+        (None, true) => Value::Assoc(BTreeMap::new()),
+        (None, false) => Value::Indexed(BTreeMap::new()),
+    };
+    set_value(name, value);
+    Ok(())
+}
+
+/// Moves a scalar into the environment so child processes see it. Arrays cannot be exported.
+pub fn export(name: &str) {
+    let mut vars = SHELL_VARS.lock().unwrap();
+    if let Some(Value::Scalar(_)) = vars.get(name) {
+        if let Some(Value::Scalar(value)) = vars.remove(name) {
+            env::set_var(name, value);
+        }
+    }
+}
+
+pub fn is_exported(name: &str) -> bool {
+    !SHELL_VARS.lock().unwrap().contains_key(name) && env::var_os(name).is_some()
+}
+
+/// The names of all shell-only variables, sorted.
+pub fn shell_names() -> Vec<String> {
+    let mut names: Vec<String> = SHELL_VARS.lock().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+pub fn unset(name: &str) {
+    SHELL_VARS.lock().unwrap().remove(name);
+    env::remove_var(name);
+}
+
+pub fn unset_element(name: &str, key: &Key) {
+    let mut vars = SHELL_VARS.lock().unwrap();
+    match (vars.get_mut(name), key) {
+        (Some(Value::Indexed(items)), Key::Index(i)) => {
+            items.remove(i);
+        }
+        (Some(Value::Assoc(items)), Key::Name(k)) => {
+            items.remove(k);
+        }
+        (Some(Value::Scalar(_)), Key::Index(0)) => {
+            vars.remove(name);
+        }
+        (None, Key::Index(0)) => env::remove_var(name),
+        _ => {}
+    }
+}
+
+/// A variable's complete state, so a temporary assignment can be undone exactly.
+pub struct Saved {
+    name: String,
+    shell: Option<Value>,
+    exported: Option<String>,
+}
+
+pub fn save(name: &str) -> Saved {
+    Saved {
+        name: name.to_string(),
+        shell: SHELL_VARS.lock().unwrap().get(name).cloned(),
+        exported: env::var(name).ok(),
+    }
+}
+
+pub fn restore(saved: Saved) {
+    let mut vars = SHELL_VARS.lock().unwrap();
+    match saved.shell {
+        Some(value) => vars.insert(saved.name.clone(), value),
+        None => vars.remove(&saved.name),
+    };
+    match saved.exported {
+        Some(value) => env::set_var(&saved.name, value),
+        None => env::remove_var(&saved.name),
+    }
+}
+
+/// Puts `name=value` in the environment for the duration of one command.
+pub fn set_exported(name: &str, value: &str) {
+    SHELL_VARS.lock().unwrap().remove(name);
+    env::set_var(name, value);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_assignment_words() {
+        let assignment = parse_assignment("list[i+1]+=x=y").unwrap();
+        assert_eq!((assignment.name, assignment.subscript, assignment.append, assignment.value), ("list", Some("i+1"), true, "x=y"));
+        let assignment = parse_assignment("name=").unwrap();
+        assert_eq!((assignment.name, assignment.subscript, assignment.append, assignment.value), ("name", None, false, ""));
+        assert!(parse_assignment("1x=2").is_none());
+        assert!(parse_assignment("name").is_none());
+        assert!(parse_assignment("a[1=2").is_none());
+    }
+}